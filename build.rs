@@ -1,4 +1,5 @@
 use pelite::resources::version_info::{Language, VersionInfo};
+use sha2::{Digest, Sha256};
 
 fn read_pe_version_info<'a>(image: &'a [u8]) -> Option<VersionInfo<'a>> {
     pelite::PeFile::from_bytes(image).ok()?.resources().ok()?.version_info().ok()
@@ -7,6 +8,7 @@ fn read_pe_version_info<'a>(image: &'a [u8]) -> Option<VersionInfo<'a>> {
 const LANG_NEUTRAL_UNICODE: Language = Language { lang_id: 0x0000, charset_id: 0x04b0 };
 fn detect_hachimi_version() {
     println!("cargo:rerun-if-env-changed=HACHIMI_VERSION");
+    println!("cargo:rerun-if-env-changed=HACHIMI_SUPPORTED_GAME_VERSIONS");
     println!("cargo:rerun-if-changed=hachimi.dll");
 
     // Allow manual override
@@ -41,7 +43,29 @@ fn compile_resources() {
     );
 }
 
+// Compressed here instead of at compile time so that a bad payload can be reported at runtime
+#[cfg(feature = "compress_dll")]
+fn compress_dll() {
+    let dll = std::fs::read("hachimi.dll").expect("hachimi.dll in project root");
+    let compressed = zstd::encode_all(dll.as_slice(), 19).expect("compressed hachimi.dll");
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(std::path::Path::new(&out_dir).join("hachimi.dll.zst"), compressed).unwrap();
+    println!("cargo:rustc-env=HACHIMI_DLL_SIZE={}", dll.len());
+}
+
+fn hash_dll() {
+    let dll = std::fs::read("hachimi.dll").expect("hachimi.dll in project root");
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(std::path::Path::new(&out_dir).join("hachimi.dll.sha256"), Sha256::digest(&dll)).unwrap();
+}
+
 fn main() {
     detect_hachimi_version();
+    hash_dll();
+    #[cfg(feature = "compress_dll")]
+    compress_dll();
+    #[cfg(feature = "debug_dll")]
+    println!("cargo:rerun-if-changed=hachimi-debug.dll");
     compile_resources();
 }
\ No newline at end of file
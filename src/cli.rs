@@ -1,11 +1,9 @@
-use std::path::{Path, PathBuf};
+use std::{collections::HashMap, path::{Path, PathBuf}};
 
+use tinyjson::JsonValue;
 use windows::{
     core::{w, HSTRING},
-    Win32::UI::{
-        Shell::ShellExecuteW,
-        WindowsAndMessaging::{MessageBoxW, IDCANCEL, MB_ICONERROR, MB_ICONINFORMATION, MB_OK, MB_OKCANCEL, SW_NORMAL}
-    }
+    Win32::UI::WindowsAndMessaging::{MessageBoxW, IDCANCEL, MB_ICONERROR, MB_ICONINFORMATION, MB_OK, MB_OKCANCEL}
 };
 
 use crate::{installer::{self, Installer, Target}, utils};
@@ -21,12 +19,31 @@ struct Args {
     launch_game: bool,
     game_args: Vec<String>,
     pre_install: bool,
-    post_install: bool
+    post_install: bool,
+    force: bool,
+    json: bool
 }
 
 enum Command {
     Install,
-    Uninstall
+    Uninstall,
+    Status,
+    Detect
+}
+
+impl Command {
+    // Read-only commands work on every target, so they don't need one to be specified
+    fn requires_target(&self) -> bool {
+        matches!(self, Command::Install | Command::Uninstall)
+    }
+}
+
+fn print_json(value: JsonValue) {
+    println!("{}", value.stringify().unwrap());
+}
+
+fn json_object(key: &str, value: &str) -> JsonValue {
+    JsonValue::Object(HashMap::from([(key.to_owned(), JsonValue::String(value.to_owned()))]))
 }
 
 #[inline]
@@ -55,23 +72,21 @@ impl Args {
             match arg.as_str() {
                 "install" => args.command = Some(Command::Install),
                 "uninstall" => args.command = Some(Command::Uninstall),
-
+                "status" => args.command = Some(Command::Status),
+                "detect" => args.command = Some(Command::Detect),
 
                 "--install-dir" => args.install_dir = Some(require_next_arg(&mut iter).into()),
                 "--target" => args.target = Some(require_next_arg(&mut iter)),
                 "--explicit-target" => {
-                    let dll_name = require_next_arg(&mut iter);
-                    args.explicit_target = Some(*Target::VALUES.iter()
-                        .filter(|t| t.dll_name() == dll_name)
-                        .next()
-                        .unwrap_or_else(|| std::process::exit(128))
-                    );
+                    args.explicit_target = Some(require_next_arg(&mut iter).parse().unwrap_or_else(|_| std::process::exit(128)));
                 },
                 "--sleep" => args.sleep = Some(require_next_arg(&mut iter).parse().unwrap_or_else(|_| std::process::exit(128))),
                 "--prompt-for-game-exit" => args.prompt_for_game_exit = true,
                 "--launch-game" => args.launch_game = true,
                 "--pre-install" => args.pre_install = true,
                 "--post-install" => args.post_install = true,
+                "--force" => args.force = true,
+                "--json" => args.json = true,
                 "--" => in_game_args = true,
 
                 _ => {
@@ -131,6 +146,8 @@ pub fn run() -> Result<bool, installer::Error> {
                 }
             }
             None
+        }).or_else(|| {
+            if command.requires_target() { None } else { Some(Target::default()) }
         }).unwrap_or_else(|| {
             unsafe {
                 MessageBoxW(
@@ -146,37 +163,84 @@ pub fn run() -> Result<bool, installer::Error> {
 
         let installer = Installer::custom(args.install_dir, explicit_target, args.target);
         let res = match command {
+            Command::Status => {
+                utils::attach_parent_console();
+                let status = installer.status();
+                if args.json {
+                    print_json(status.to_json());
+                }
+                else {
+                    println!(
+                        "Install dir: {}",
+                        installer.install_dir.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "None".to_owned())
+                    );
+                    println!("Hachimi target: {}", status.hachimi_target.map(|t| t.dll_name()).unwrap_or("None"));
+                    println!("Installed version: {}", status.installed_version.as_deref().unwrap_or("None"));
+                    println!("Current target occupied: {}", status.current_target_occupied);
+                }
+
+                // Lets scripts branch on the exit code
+                if status.hachimi_target.is_none() {
+                    std::process::exit(1);
+                }
+                return Ok(true);
+            },
+            Command::Detect => {
+                utils::attach_parent_console();
+                let dirs = Installer::detect_install_dirs();
+                if args.json {
+                    print_json(JsonValue::Array(
+                        dirs.iter().map(|dir| JsonValue::String(dir.display().to_string())).collect()
+                    ));
+                }
+                else {
+                    for dir in &dirs {
+                        println!("{}", dir.display());
+                    }
+                }
+
+                if dirs.is_empty() {
+                    std::process::exit(1);
+                }
+                return Ok(true);
+            },
             Command::Install => {
-                let mut res = Ok(());
+                let mut res = installer.validate_custom_target();
                 if args.pre_install {
                     res = res.and_then(|_| installer.pre_install());
                 }
-                res = res.and_then(|_| installer.install());
+                let mut res = res.and_then(|_| installer.install());
                 if args.post_install {
-                    res = res.and_then(|_| installer.post_install());
+                    res = res.and_then(|outcome| installer.post_install().map(|_| outcome));
                 }
-                res
+                res.map(Some)
             },
-            Command::Uninstall => installer.uninstall()
+            Command::Uninstall => if args.force {
+                installer.uninstall_force().map(|_| None)
+            }
+            else {
+                installer.uninstall().map(|_| None)
+            }
         };
+
+        if args.json {
+            utils::attach_parent_console();
+            print_json(match &res {
+                Ok(Some(outcome)) => outcome.to_json(),
+                Ok(None) => json_object("outcome", "uninstalled"),
+                Err(e) => json_object("error", &e.to_string())
+            });
+        }
         if let Err(e) = res {
-            unsafe { MessageBoxW(None, &HSTRING::from(e.to_string()), w!("Hachimi Installer"), MB_ICONERROR | MB_OK); }
+            if !args.json {
+                unsafe { MessageBoxW(None, &HSTRING::from(e.to_string()), w!("Hachimi Installer"), MB_ICONERROR | MB_OK); }
+            }
             return Err(e);
         }
 
         if args.launch_game {
-            let game_dir = installer.install_dir.unwrap();
-            let exe_path = game_dir.join("umamusume.exe");
-            unsafe {
-                ShellExecuteW(
-                    None,
-                    None,
-                    &HSTRING::from(exe_path.to_str().unwrap()),
-                    &HSTRING::from(args.game_args.join(" ")),
-                    &HSTRING::from(game_dir.to_str().unwrap()),
-                    SW_NORMAL
-                );
-            }
+            // Game args can only be passed to the exe directly
+            _ = installer.launch_game_exe(&args.game_args.join(" "));
         }
 
         Ok(true)
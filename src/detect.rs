@@ -0,0 +1,200 @@
+use std::path::PathBuf;
+
+use registry::{Data, Hive, Security};
+use tinyjson::JsonValue;
+
+use crate::{i18n::Lang, installer::Installer, utils};
+
+/// Detectors are stored in `Installer`, which is sent to worker threads by the async API.
+pub trait InstallDirDetector: Send + Sync {
+    fn detect(&self) -> Option<PathBuf>;
+
+    fn detect_all(&self) -> Vec<PathBuf> {
+        self.detect().into_iter().collect()
+    }
+}
+
+pub fn default_detectors() -> Vec<Box<dyn InstallDirDetector>> {
+    vec![
+        Box::new(EnvDetector),
+        Box::new(DmmDetector),
+        Box::new(MsStoreDetector),
+        Box::new(ProcessDetector)
+    ]
+}
+
+pub struct EnvDetector;
+
+impl InstallDirDetector for EnvDetector {
+    fn detect(&self) -> Option<PathBuf> {
+        let path = PathBuf::from(std::env::var_os("HACHIMI_INSTALL_DIR")?);
+        if path.is_dir() {
+            Some(path)
+        }
+        else {
+            None
+        }
+    }
+}
+
+pub struct DmmDetector;
+
+/// DMM product IDs of the game, in order of preference.
+pub const DEFAULT_DMM_PRODUCT_IDS: &[&str] = &["umamusume"];
+
+impl DmmDetector {
+    /// The comma separated `HACHIMI_DMM_PRODUCT_ID` env var is tried before the defaults.
+    pub fn product_ids() -> Vec<String> {
+        let mut ids: Vec<String> = std::env::var("HACHIMI_DMM_PRODUCT_ID")
+            .map(|ids| ids.split(',').map(|id| id.trim().to_owned()).filter(|id| !id.is_empty()).collect())
+            .unwrap_or_default();
+        for id in DEFAULT_DMM_PRODUCT_IDS {
+            if !ids.iter().any(|i| i == id) {
+                ids.push(id.to_string());
+            }
+        }
+        ids
+    }
+
+    pub fn read_install_dirs(&self) -> Result<Vec<PathBuf>, DetectError> {
+        let product_ids = Self::product_ids();
+        let app_data_dir = utils::get_app_data_dir().ok_or(DetectError::DmmNotFound)?;
+        let mut dmm_config_path = app_data_dir.join("dmmgameplayer5");
+        dmm_config_path.push("dmmgame.cnf");
+
+        let config_str = std::fs::read_to_string(dmm_config_path).map_err(|_| DetectError::DmmNotFound)?;
+        let Ok(JsonValue::Object(config)) = config_str.parse() else {
+            return Err(DetectError::ResolveFailed);
+        };
+        let Some(JsonValue::Array(config_contents)) = config.get("contents") else {
+            return Err(DetectError::ResolveFailed);
+        };
+
+        let mut found = false;
+        let mut dirs = Vec::new();
+        for value in config_contents {
+            let JsonValue::Object(game) = value else {
+                continue;
+            };
+
+            let Some(JsonValue::String(product_id)) = game.get("productId") else {
+                continue;
+            };
+            let Some(rank) = product_ids.iter().position(|id| id == product_id) else {
+                continue;
+            };
+            found = true;
+
+            let Some(JsonValue::Object(detail)) = game.get("detail") else {
+                continue;
+            };
+            let Some(JsonValue::String(path_str)) = detail.get("path") else {
+                continue;
+            };
+
+            let path = PathBuf::from(path_str);
+            if Installer::is_valid_install_dir(&path) {
+                dirs.push((rank, path));
+            }
+        }
+
+        if !found {
+            return Err(DetectError::AppNotInstalled);
+        }
+        if dirs.is_empty() {
+            return Err(DetectError::ResolveFailed);
+        }
+        dirs.sort_by_key(|(rank, _)| *rank);
+        Ok(dirs.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl InstallDirDetector for DmmDetector {
+    fn detect(&self) -> Option<PathBuf> {
+        self.detect_all().into_iter().next()
+    }
+
+    fn detect_all(&self) -> Vec<PathBuf> {
+        self.read_install_dirs().unwrap_or_default()
+    }
+}
+
+/// Finds Microsoft Store installs through the AppX package repository. Packages aren't
+/// matched by name; any package that contains the game's executable is accepted.
+pub struct MsStoreDetector;
+
+const APPX_PACKAGES_KEY: &str =
+    r"Software\Classes\Local Settings\Software\Microsoft\Windows\CurrentVersion\AppModel\Repository\Packages";
+
+impl InstallDirDetector for MsStoreDetector {
+    fn detect(&self) -> Option<PathBuf> {
+        self.detect_all().into_iter().next()
+    }
+
+    fn detect_all(&self) -> Vec<PathBuf> {
+        let Ok(packages) = Hive::CurrentUser.open(APPX_PACKAGES_KEY, Security::Read) else {
+            return Vec::new();
+        };
+
+        let mut dirs = Vec::new();
+        for package in packages.keys().flatten() {
+            let Ok(package) = package.open(Security::Read) else {
+                continue;
+            };
+            let Ok(Data::String(root_folder)) = package.value("PackageRootFolder") else {
+                continue;
+            };
+
+            let path = PathBuf::from(root_folder.to_string_lossy());
+            if Installer::is_valid_install_dir(&path) {
+                dirs.push(path);
+            }
+        }
+        dirs
+    }
+}
+
+pub struct ProcessDetector;
+
+impl InstallDirDetector for ProcessDetector {
+    fn detect(&self) -> Option<PathBuf> {
+        let exe_path = utils::get_game_process_path()?;
+        Some(exe_path.parent()?.to_owned())
+    }
+}
+
+#[derive(Debug)]
+pub enum DetectError {
+    DmmNotFound,
+    AppNotInstalled,
+    ResolveFailed
+}
+
+impl std::fmt::Display for DetectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match Lang::current() {
+            Lang::English => self.fmt_english(f),
+            Lang::Japanese => self.fmt_japanese(f)
+        }
+    }
+}
+
+impl DetectError {
+    fn fmt_english(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DetectError::DmmNotFound => write!(f, "DMM Game Player is not installed"),
+            DetectError::AppNotInstalled => write!(f, "The game is not installed in DMM Game Player"),
+            DetectError::ResolveFailed => write!(f, "Failed to resolve the game's install location")
+        }
+    }
+
+    fn fmt_japanese(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DetectError::DmmNotFound => write!(f, "DMM Game Playerがインストールされていません"),
+            DetectError::AppNotInstalled => write!(f, "ゲームがDMM Game Playerにインストールされていません"),
+            DetectError::ResolveFailed => write!(f, "ゲームのインストール先を特定できませんでした")
+        }
+    }
+}
+
+impl std::error::Error for DetectError {}
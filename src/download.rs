@@ -0,0 +1,89 @@
+use std::{io::Read, time::Duration};
+
+use tinyjson::JsonValue;
+
+use crate::installer::Error;
+
+const RELEASES_API_URL: &str = "https://api.github.com/repos/Hachimi-Hachimi/Hachimi/releases";
+const DLL_ASSET_NAME: &str = "hachimi.dll";
+
+/// Retries connection failures and timeouts with exponential backoff, starting at 1 second.
+/// HTTP errors, e.g. a 404 for an unknown tag, are returned right away.
+pub fn download_release_dll_with_retry(
+    tag: Option<&str>, attempts: u32, progress: &mut dyn FnMut(u64, Option<u64>)
+) -> Result<Vec<u8>, Error> {
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 1;
+    loop {
+        match download_release_dll(tag, progress) {
+            Err(e) if is_transient_error(&e) && attempt < attempts => {
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            res => return res
+        }
+    }
+}
+
+/// Whether the error means that GitHub couldn't be reached, as opposed to it rejecting the request.
+pub fn is_transient_error(e: &Error) -> bool {
+    match e {
+        Error::DownloadError(e) => e.is_connect() || e.is_timeout(),
+        // I/O errors here come from reading the response body
+        Error::IoError(e) => e.kind() == std::io::ErrorKind::TimedOut,
+        _ => false
+    }
+}
+
+pub fn download_release_dll(tag: Option<&str>, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<Vec<u8>, Error> {
+    let url = match tag {
+        Some(tag) => format!("{}/tags/{}", RELEASES_API_URL, tag),
+        None => format!("{}/latest", RELEASES_API_URL)
+    };
+
+    // GitHub's API rejects requests without a user agent
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("hachimi_installer/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+
+    let release_str = client.get(url).send()?.error_for_status()?.text()?;
+    let Ok(JsonValue::Object(release)) = release_str.parse() else {
+        return Err(Error::ReleaseAssetNotFound);
+    };
+    let Some(JsonValue::Array(assets)) = release.get("assets") else {
+        return Err(Error::ReleaseAssetNotFound);
+    };
+
+    for asset in assets {
+        let JsonValue::Object(asset) = asset else {
+            continue;
+        };
+
+        let Some(JsonValue::String(name)) = asset.get("name") else {
+            continue;
+        };
+        if name != DLL_ASSET_NAME {
+            continue;
+        }
+
+        let Some(JsonValue::String(download_url)) = asset.get("browser_download_url") else {
+            continue;
+        };
+        let mut res = client.get(download_url).send()?.error_for_status()?;
+        let total = res.content_length();
+        let mut dll = Vec::with_capacity(total.unwrap_or(0) as usize);
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let read = res.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            dll.extend_from_slice(&buf[..read]);
+            progress(dll.len() as u64, total);
+        }
+        return Ok(dll);
+    }
+
+    Err(Error::ReleaseAssetNotFound)
+}
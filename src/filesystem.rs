@@ -0,0 +1,278 @@
+use std::{fs::File, io::{self, Write}, path::{Path, PathBuf}, time::SystemTime};
+
+pub struct FileMetadata {
+    pub is_dir: bool,
+    pub len: u64,
+    pub readonly: bool,
+    pub modified: Option<SystemTime>
+}
+
+pub trait FileSystem: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()>;
+
+    /// Resolves symlinks and junctions. By default, the path is returned as-is.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_owned())
+    }
+
+    /// Reports the number of bytes written so far. By default, the data is written all at once.
+    fn write_with_progress(&self, path: &Path, data: &[u8], progress: &mut dyn FnMut(u64)) -> io::Result<()> {
+        self.write(path, data)?;
+        progress(data.len() as u64);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).map(|m| !m.is_dir).unwrap_or(false)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).map(|m| m.is_dir).unwrap_or(false)
+    }
+}
+
+pub struct RealFileSystem;
+
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+impl FileSystem for RealFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+
+    fn write_with_progress(&self, path: &Path, data: &[u8], progress: &mut dyn FnMut(u64)) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        let mut written = 0;
+        for chunk in data.chunks(WRITE_CHUNK_SIZE) {
+            file.write_all(chunk)?;
+            written += chunk.len() as u64;
+            progress(written);
+        }
+        file.sync_all()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to).map(|_| ())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(FileMetadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            modified: metadata.modified().ok()
+        })
+    }
+
+    // On Windows this only toggles FILE_ATTRIBUTE_READONLY
+    #[allow(clippy::permissions_set_readonly_false)]
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+        let mut permissions = std::fs::metadata(path)?.permissions();
+        permissions.set_readonly(readonly);
+        std::fs::set_permissions(path, permissions)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::{collections::{HashMap, HashSet}, io, path::{Path, PathBuf}, sync::Mutex};
+
+    use super::{FileMetadata, FileSystem};
+    use crate::utils;
+
+    /// In-memory filesystem for tests. Paths are stored without the extended prefix, so that
+    /// the installer's extended paths and the test's regular paths refer to the same files.
+    #[derive(Default)]
+    pub struct MemoryFileSystem {
+        files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+        dirs: Mutex<HashSet<PathBuf>>,
+        readonly: Mutex<HashSet<PathBuf>>,
+        links: Mutex<HashMap<PathBuf, PathBuf>>
+    }
+
+    fn key(path: &Path) -> PathBuf {
+        utils::strip_extended_prefix(path)
+    }
+
+    fn not_found() -> io::Error {
+        io::Error::from(io::ErrorKind::NotFound)
+    }
+
+    impl MemoryFileSystem {
+        /// Adds a file, creating its parent directories.
+        pub fn add_file(&self, path: impl AsRef<Path>, data: &[u8]) {
+            let path = key(path.as_ref());
+            self.create_dir_all(path.parent().unwrap()).unwrap();
+            self.files.lock().unwrap().insert(path, data.to_vec());
+        }
+
+        /// Makes `canonicalize` resolve paths under `link` to `target`, like a junction.
+        pub fn add_link(&self, link: impl AsRef<Path>, target: impl AsRef<Path>) {
+            self.links.lock().unwrap().insert(key(link.as_ref()), key(target.as_ref()));
+        }
+
+        pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+            self.files.lock().unwrap().get(&key(path.as_ref())).cloned()
+        }
+
+        fn check_writable(&self, path: &Path) -> io::Result<()> {
+            if self.readonly.lock().unwrap().contains(path) {
+                return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+            }
+            Ok(())
+        }
+
+        fn check_parent(&self, path: &Path) -> io::Result<()> {
+            match path.parent() {
+                Some(parent) if !self.dirs.lock().unwrap().contains(parent) => Err(not_found()),
+                _ => Ok(())
+            }
+        }
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.file(path).ok_or_else(not_found)
+        }
+
+        fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            let path = key(path);
+            self.check_parent(&path)?;
+            self.check_writable(&path)?;
+            self.files.lock().unwrap().insert(path, data.to_vec());
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            let path = key(path);
+            self.check_writable(&path)?;
+            self.files.lock().unwrap().remove(&path).map(|_| ()).ok_or_else(not_found)
+        }
+
+        // Replaces an existing file, same as std::fs::rename on Windows
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let (from, to) = (key(from), key(to));
+            self.check_parent(&to)?;
+            self.check_writable(&to)?;
+            let mut files = self.files.lock().unwrap();
+            let data = files.remove(&from).ok_or_else(not_found)?;
+            files.insert(to.clone(), data);
+
+            let mut readonly = self.readonly.lock().unwrap();
+            if readonly.remove(&from) {
+                readonly.insert(to);
+            }
+            Ok(())
+        }
+
+        fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let data = self.read(from)?;
+            self.write(to, &data)
+        }
+
+        fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            let mut dirs = self.dirs.lock().unwrap();
+            for dir in key(path).ancestors() {
+                dirs.insert(dir.to_owned());
+            }
+            Ok(())
+        }
+
+        fn remove_dir(&self, path: &Path) -> io::Result<()> {
+            let path = key(path);
+            let has_children = self.files.lock().unwrap().keys().any(|p| p.parent() == Some(path.as_path())) ||
+                self.dirs.lock().unwrap().iter().any(|p| p.parent() == Some(path.as_path()));
+            if has_children {
+                return Err(io::Error::other("directory not empty"));
+            }
+            if !self.dirs.lock().unwrap().remove(&path) {
+                return Err(not_found());
+            }
+            Ok(())
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+            let path = key(path);
+            if let Some(data) = self.files.lock().unwrap().get(&path) {
+                return Ok(FileMetadata {
+                    is_dir: false,
+                    len: data.len() as u64,
+                    readonly: self.readonly.lock().unwrap().contains(&path),
+                    modified: None
+                });
+            }
+            if self.dirs.lock().unwrap().contains(&path) {
+                return Ok(FileMetadata { is_dir: true, len: 0, readonly: false, modified: None });
+            }
+            Err(not_found())
+        }
+
+        fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+            let path = key(path);
+            if !self.files.lock().unwrap().contains_key(&path) {
+                return Err(not_found());
+            }
+            let mut readonly_files = self.readonly.lock().unwrap();
+            if readonly {
+                readonly_files.insert(path);
+            }
+            else {
+                readonly_files.remove(&path);
+            }
+            Ok(())
+        }
+
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            let path = key(path);
+            let resolved = self.links.lock().unwrap().iter()
+                .find_map(|(link, target)| {
+                    let rest = path.strip_prefix(link).ok()?;
+                    Some(if rest.as_os_str().is_empty() { target.clone() } else { target.join(rest) })
+                })
+                .unwrap_or(path);
+            if !self.exists(&resolved) {
+                return Err(not_found());
+            }
+            Ok(resolved)
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{installer::{self, Installer}, resource::*, utils};
-use windows::{core::{w, HSTRING}, Win32::{
+use crate::{i18n::{self, Lang, Msg}, installer::{self, InstallOutcome, Installer}, resource::*, utils};
+use windows::{core::HSTRING, Win32::{
     Foundation::{HWND, LPARAM, WPARAM},
     System::LibraryLoader::GetModuleHandleW,
     UI::{Input::KeyboardAndMouse::EnableWindow, WindowsAndMessaging::{
@@ -7,13 +7,15 @@ use windows::{core::{w, HSTRING}, Win32::{
         GetWindowLongPtrW, LoadIconW, MessageBoxW, PostQuitMessage, SendMessageW,
         SetWindowLongPtrW,SetWindowTextW, ShowWindow, TranslateMessage,
         CBN_SELCHANGE, CB_ADDSTRING, CB_DELETESTRING, CB_GETCURSEL, CB_INSERTSTRING, CB_SETCURSEL,
-        GWLP_USERDATA, ICON_BIG, IDOK, IDYES, MB_ICONERROR, MB_ICONINFORMATION, MB_ICONWARNING,
+        GWLP_USERDATA, ICON_BIG, IDOK, IDYES, MB_DEFBUTTON2, MB_ICONERROR, MB_ICONINFORMATION, MB_ICONWARNING,
         MB_OK, MB_OKCANCEL, MB_YESNO, MSG, SW_SHOW, WM_CLOSE, WM_COMMAND, WM_INITDIALOG, WM_SETICON
     }}
 }};
 
 pub fn run() -> Result<(), windows::core::Error> {
+    Installer::set_language(Lang::from_system());
     let mut installer = Box::new(Installer::default());
+    installer.prevent_downgrade = true;
 
     let instance = unsafe { GetModuleHandleW(None)? };
     let dialog = unsafe {
@@ -38,26 +40,49 @@ fn get_installer(dialog: HWND) -> &'static mut Installer {
     unsafe { (GetWindowLongPtrW(dialog, GWLP_USERDATA) as *mut Installer).as_mut().unwrap() }
 }
 
+fn check_write_access(dialog: HWND, installer: &Installer) {
+    if installer.is_msstore_install() {
+        unsafe {
+            MessageBoxW(
+                dialog,
+                &HSTRING::from(i18n::tr(Msg::MsStoreProtected)),
+                &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                MB_ICONWARNING | MB_OK
+            );
+        }
+    }
+    else if installer.install_dir.is_some() && !installer.can_write_install_dir() {
+        unsafe {
+            MessageBoxW(
+                dialog,
+                &HSTRING::from(i18n::tr(Msg::WriteProtected)),
+                &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                MB_ICONWARNING | MB_OK
+            );
+        }
+    }
+}
+
 fn update_target(dialog: HWND, target_combo: HWND, index: usize) {
     let installer = get_installer(dialog);
     let target = installer::Target::VALUES[index];
-    let mut installed = false;
-    let label = if let Some(version_info) = installer.get_target_version_info(target) {
-        installed = true;
-        version_info.version.unwrap_or_else(|| "Unknown".to_owned())
-    }
-    else {
-        "None".to_owned()
+    let version_info_res = installer.get_target_version_info_result(target);
+    let installed = version_info_res.is_some();
+    let label = match &version_info_res {
+        Some(Ok(version_info)) => version_info.get_version().unwrap_or(i18n::tr(Msg::Unknown)).to_owned(),
+        Some(Err(_)) => i18n::tr(Msg::Corrupt).to_owned(),
+        None => i18n::tr(Msg::None).to_owned()
     };
+    let version_info = version_info_res.and_then(|res| res.ok());
 
     let installed_static = unsafe { GetDlgItem(dialog, IDC_INSTALLED).unwrap() };
     unsafe {
-        _ = SetWindowTextW(installed_static, &HSTRING::from(format!("Installed: {}", label)));
+        _ = SetWindowTextW(installed_static, &HSTRING::from(i18n::tr_args(Msg::Installed, &[&label])));
         _ = EnableWindow(GetDlgItem(dialog, IDC_UNINSTALL).unwrap(), installed);
     }
         
 
-    let label = installer.get_target_display_label(target);
+    let label = installer.target_label_cached(target, version_info.as_ref());
     unsafe {
         SendMessageW(target_combo, CB_DELETESTRING, WPARAM(index), None);
         SendMessageW(target_combo, CB_INSERTSTRING, WPARAM(index), LPARAM(HSTRING::from(label).as_ptr() as _));
@@ -91,17 +116,17 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
             let packaged_ver_static = GetDlgItem(dialog, IDC_PACKAGED_VER).unwrap();
             _ = SetWindowTextW(
                 packaged_ver_static,
-                &HSTRING::from(format!("Packaged version: {}", env!("HACHIMI_VERSION")))
+                &HSTRING::from(i18n::tr_args(Msg::PackagedVersion, &[&env!("HACHIMI_VERSION")]))
             );
 
             // Init targets
             let target_combo = GetDlgItem(dialog, IDC_TARGET).unwrap();
-            let mut default_target = 0;
+            let mut default_target = installer.recommended_target().unwrap_or_default() as usize;
             let mut default_target_set = false;
             let mut multiple_installs = false;
             for (i, target) in installer::Target::VALUES.into_iter().enumerate() {
                 let label = if let Some(version_info) = installer.get_target_version_info(*target) {
-                    if version_info.is_hachimi() {
+                    if installer.is_hachimi(&version_info) {
                         if default_target_set {
                             // Already set; multiple installations detected!
                             multiple_installs = true;
@@ -118,28 +143,45 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
                     target_combo, CB_ADDSTRING, None, LPARAM(HSTRING::from(label).as_ptr() as _)
                 );
             }
-            // Defaults to already installed Hachimi dll, if any
+            // Defaults to already installed Hachimi dll, if any, otherwise the recommended target
             update_target(dialog, target_combo, default_target);
 
             // Show notice if install dir is not detected
             if installer.install_dir.is_none() {
+                let reason = Installer::detect_install_dir_result()
+                    .err()
+                    .map(|e| format!(" ({})", e))
+                    .unwrap_or_default();
                 MessageBoxW(
                     dialog,
-                    w!("Failed to detect the game's install location. Please select it manually."),
-                    w!("Warning"),
+                    &HSTRING::from(i18n::tr_args(Msg::DetectFailed, &[&reason])),
+                    &HSTRING::from(i18n::tr(Msg::WarningTitle)),
                     MB_ICONWARNING | MB_OK
                 );
             }
 
-            // Show notice for multiple installs
+            check_write_access(dialog, installer);
+
+            // Offer to clean up multiple installs
             if multiple_installs {
-                MessageBoxW(
+                let keep = installer::Target::VALUES[default_target];
+                let res = MessageBoxW(
                     dialog,
-                    w!("Multiple installations of Hachimi detected! \
-                        Please uninstall one of them, otherwise the game will not work correctly."),
-                    w!("Warning"),
-                    MB_ICONWARNING | MB_OK
+                    &HSTRING::from(i18n::tr_args(Msg::MultipleInstalls, &[&keep.dll_name()])),
+                    &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                    MB_ICONWARNING | MB_YESNO
                 );
+                if res == IDYES {
+                    if let Err(e) = installer.cleanup_duplicate_installs(keep) {
+                        MessageBoxW(dialog, &HSTRING::from(e.to_string()), &HSTRING::from(i18n::tr(Msg::ErrorTitle)), MB_ICONERROR | MB_OK);
+                    }
+
+                    // Refresh all of the labels, then restore the selection
+                    for i in 0..installer::Target::VALUES.len() {
+                        update_target(dialog, target_combo, i);
+                    }
+                    update_target(dialog, target_combo, default_target);
+                }
             }
 
             1
@@ -164,6 +206,8 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
                     _ = SetWindowTextW(install_path_edit, &HSTRING::from(path.to_str().unwrap()));
 
                     installer.install_dir = Some(path);
+                    _ = installer.save_config();
+                    check_write_access(dialog, installer);
                     update_target(dialog, GetDlgItem(dialog, IDC_TARGET).unwrap(), installer.target as _);
                 }
 
@@ -180,8 +224,8 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
                         if target != installer.target {
                             MessageBoxW(
                                 dialog,
-                                &HSTRING::from(format!("Hachimi is already installed as {}", target.dll_name())),
-                                w!("Error"),
+                                &HSTRING::from(i18n::tr_args(Msg::AlreadyInstalledAs, &[&target.dll_name()])),
+                                &HSTRING::from(i18n::tr(Msg::ErrorTitle)),
                                 MB_ICONERROR | MB_OK
                             );
                             return 0;
@@ -190,23 +234,43 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
                     if installer.is_current_target_installed() {
                         let res = MessageBoxW(
                             dialog,
-                            &HSTRING::from(format!("Replace {}?", installer.target.dll_name())),
-                            w!("Install"),
+                            &HSTRING::from(i18n::tr_args(Msg::ReplaceTarget, &[&installer.target.dll_name()])),
+                            &HSTRING::from(i18n::tr(Msg::InstallTitle)),
                             MB_ICONINFORMATION | MB_OKCANCEL
                         );
                         if res != IDOK {
                             return 0;
                         }
                     }
-                    match installer.pre_install()
-                        .and_then(|_| installer.install())
-                        .and_then(|_| installer.post_install())
-                    {
-                        Ok(_) => {
-                            MessageBoxW(dialog, w!("Install completed."), w!("Success"), MB_ICONINFORMATION | MB_OK);
+                    let res = match installer.pre_install().and_then(|_| installer.install()) {
+                        Err(e @ installer::Error::WouldDowngrade { .. }) => {
+                            let res = MessageBoxW(
+                                dialog,
+                                &HSTRING::from(i18n::tr_args(Msg::DowngradeAnyway, &[&e])),
+                                &HSTRING::from(i18n::tr(Msg::InstallTitle)),
+                                MB_ICONWARNING | MB_YESNO
+                            );
+                            if res != IDYES {
+                                return 0;
+                            }
+                            installer.install_force()
+                        },
+                        res => res
+                    };
+                    match res.and_then(|outcome| installer.post_install().map(|_| outcome)) {
+                        Ok(outcome) => {
+                            _ = installer.save_config();
+                            let message = match outcome {
+                                InstallOutcome::Overwritten { previous: Some(previous) } if installer.is_hachimi(&previous) => i18n::tr_args(
+                                    Msg::UpdatedFrom,
+                                    &[&previous.get_version().unwrap_or(i18n::tr(Msg::Unknown)), &env!("HACHIMI_VERSION")]
+                                ),
+                                _ => i18n::tr(Msg::InstallCompleted).to_owned()
+                            };
+                            MessageBoxW(dialog, &HSTRING::from(message), &HSTRING::from(i18n::tr(Msg::SuccessTitle)), MB_ICONINFORMATION | MB_OK);
                         },
                         Err(e) => {
-                            MessageBoxW(dialog, &HSTRING::from(e.to_string()), w!("Error"), MB_ICONERROR | MB_OK);
+                            MessageBoxW(dialog, &HSTRING::from(e.to_string()), &HSTRING::from(i18n::tr(Msg::ErrorTitle)), MB_ICONERROR | MB_OK);
                         }
                     }
                     update_target(dialog, GetDlgItem(dialog, IDC_TARGET).unwrap(), installer.target as _);
@@ -214,44 +278,63 @@ unsafe extern "system" fn dlg_proc(dialog: HWND, message: u32, wparam: WPARAM, l
 
                 IDC_UNINSTALL => {
                     let installer = get_installer(dialog);
-                    let res = MessageBoxW(
-                        dialog,
-                        &HSTRING::from(format!("Delete {}?", installer.target.dll_name())),
-                        w!("Uninstall"),
-                        MB_ICONINFORMATION | MB_OKCANCEL
+                    let Some(version_info) = installer.get_target_version_info(installer.target) else {
+                        let message = installer::Error::NotInstalled.to_string();
+                        MessageBoxW(dialog, &HSTRING::from(message), &HSTRING::from(i18n::tr(Msg::ErrorTitle)), MB_ICONERROR | MB_OK);
+                        return 0;
+                    };
+                    let is_hachimi = installer.is_hachimi(&version_info);
+                    let mut message = i18n::tr_args(
+                        if is_hachimi { Msg::DeleteTarget } else { Msg::DeleteNotHachimi },
+                        &[&installer.target.dll_name()]
                     );
+                    if installer.warn_steam_managed() {
+                        message.push_str("\n\n");
+                        message.push_str(i18n::tr(Msg::SteamManagedUninstall));
+                    }
+                    // Deleting something that isn't Hachimi is destructive, so default to Cancel
+                    let style = if is_hachimi {
+                        MB_ICONINFORMATION | MB_OKCANCEL
+                    }
+                    else {
+                        MB_ICONWARNING | MB_OKCANCEL | MB_DEFBUTTON2
+                    };
+                    let res = MessageBoxW(dialog, &HSTRING::from(message), &HSTRING::from(i18n::tr(Msg::UninstallTitle)), style);
                     if res == IDOK {
-                        let version_info_opt = installer.get_target_version_info(installer.target);
-                        if let Err(e) = installer.uninstall() {
-                            MessageBoxW(dialog, &HSTRING::from(e.to_string()), w!("Error"), MB_ICONERROR | MB_OK);
+                        let res = if is_hachimi {
+                            installer.uninstall()
+                        }
+                        else {
+                            installer.uninstall_force()
+                        };
+                        if let Err(e) = res {
+                            MessageBoxW(dialog, &HSTRING::from(e.to_string()), &HSTRING::from(i18n::tr(Msg::ErrorTitle)), MB_ICONERROR | MB_OK);
                             return 0;
                         }
                         update_target(dialog, GetDlgItem(dialog, IDC_TARGET).unwrap(), installer.target as _);
 
-                        if let Some(version_info) = version_info_opt {
-                            if !version_info.is_hachimi() {
-                                return 0;
-                            }
+                        if !is_hachimi {
+                            return 0;
+                        }
 
-                            // Check if the hachimi data dir exists and prompt user to delete it
-                            let hachimi_dir = installer.install_dir.as_ref().unwrap().join("hachimi");
-                            let Ok(metadata) = std::fs::metadata(&hachimi_dir) else {
-                                return 0;
-                            };
+                        // Check if the hachimi data dir exists and prompt user to delete it
+                        let hachimi_dir = installer.get_hachimi_dir().unwrap();
+                        let Ok(metadata) = std::fs::metadata(&hachimi_dir) else {
+                            return 0;
+                        };
+
+                        if metadata.is_dir() {
+                            let res = MessageBoxW(
+                                dialog,
+                                &HSTRING::from(i18n::tr(Msg::DeleteDataDir)),
+                                &HSTRING::from(i18n::tr(Msg::UninstallTitle)),
+                                MB_ICONINFORMATION | MB_YESNO
+                            );
 
-                            if metadata.is_dir() {
-                                let res = MessageBoxW(
-                                    dialog,
-                                    w!("Do you also want to delete Hachimi's data directory?"),
-                                    w!("Uninstall"),
-                                    MB_ICONINFORMATION | MB_YESNO
-                                );
-
-                                if res == IDYES {
-                                    if let Err(e) = std::fs::remove_dir_all(&hachimi_dir) {
-                                        MessageBoxW(dialog, &HSTRING::from(e.to_string()), w!("Error"), MB_ICONERROR | MB_OK);
-                                        return 0;
-                                    }
+                            if res == IDYES {
+                                if let Err(e) = std::fs::remove_dir_all(&hachimi_dir) {
+                                    MessageBoxW(dialog, &HSTRING::from(e.to_string()), &HSTRING::from(i18n::tr(Msg::ErrorTitle)), MB_ICONERROR | MB_OK);
+                                    return 0;
                                 }
                             }
                         }
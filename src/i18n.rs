@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use windows::Win32::Globalization::GetUserDefaultUILanguage;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Lang {
+    #[default]
+    English,
+    Japanese
+}
+
+// Error messages are formatted without access to the installer, so the language is global
+static CURRENT_LANG: AtomicU8 = AtomicU8::new(Lang::English as u8);
+
+const LANG_JAPANESE: u16 = 0x11;
+
+impl Lang {
+    pub fn current() -> Lang {
+        match CURRENT_LANG.load(Ordering::Relaxed) {
+            1 => Lang::Japanese,
+            _ => Lang::English
+        }
+    }
+
+    pub fn set_current(self) {
+        CURRENT_LANG.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn from_system() -> Lang {
+        // The low 10 bits are the primary language ID
+        let lang_id = unsafe { GetUserDefaultUILanguage() };
+        if lang_id & 0x3ff == LANG_JAPANESE {
+            Lang::Japanese
+        }
+        else {
+            Lang::English
+        }
+    }
+}
+
+/// Messages may contain `{}` placeholders, see `tr_args`.
+#[derive(Clone, Copy)]
+pub enum Msg {
+    WarningTitle,
+    ErrorTitle,
+    InstallTitle,
+    UninstallTitle,
+    SuccessTitle,
+    RedirectionEnabledTitle,
+    Unknown,
+    None,
+    Corrupt,
+    Installed,
+    PackagedVersion,
+    InstallCompleted,
+    UpdatedFrom,
+    DeleteTarget,
+    DeleteNotHachimi,
+    SteamManagedUninstall,
+    MsStoreProtected,
+    WriteProtected,
+    DetectFailed,
+    UnsupportedGameVersion,
+    AnticheatDetected,
+    AlreadyInstalledAs,
+    ReplaceTarget,
+    DowngradeAnyway,
+    DeleteDataDir,
+    MultipleInstalls,
+    EnableRedirection,
+    RestartToApply,
+    RegistryOpenFailed
+}
+
+pub fn tr(msg: Msg) -> &'static str {
+    match Lang::current() {
+        Lang::English => tr_english(msg),
+        Lang::Japanese => tr_japanese(msg)
+    }
+}
+
+fn tr_english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::WarningTitle => "Warning",
+        Msg::ErrorTitle => "Error",
+        Msg::InstallTitle => "Install",
+        Msg::UninstallTitle => "Uninstall",
+        Msg::SuccessTitle => "Success",
+        Msg::RedirectionEnabledTitle => "DLL redirection enabled",
+        Msg::Unknown => "Unknown",
+        Msg::None => "None",
+        Msg::Corrupt => "Corrupt",
+        Msg::Installed => "Installed: {}",
+        Msg::PackagedVersion => "Packaged version: {}",
+        Msg::InstallCompleted => "Install completed.",
+        Msg::UpdatedFrom => "Updated from {} to {}.",
+        Msg::DeleteTarget => "Delete {}?",
+        Msg::DeleteNotHachimi => "{} is not Hachimi. Delete it anyway?",
+        Msg::SteamManagedUninstall => "The game is managed by Steam, which might restore the file. \
+            Please exit Steam before uninstalling.",
+        Msg::MsStoreProtected => "The game was installed from the Microsoft Store. Its folder is protected, \
+            so installing Hachimi might not work without changing the folder's permissions.",
+        Msg::WriteProtected => "The install location is write-protected. Please restart the installer as administrator.",
+        Msg::DetectFailed => "Failed to detect the game's install location{}. Please select it manually.",
+        Msg::UnsupportedGameVersion => "Game version {} has not been tested with this version of Hachimi. \
+            It might not work correctly until Hachimi is updated.",
+        Msg::AnticheatDetected => "An anti-cheat component was found in the game's folder ({}). \
+            Using Hachimi might cause crashes or get your account banned. Proceed at your own risk.",
+        Msg::AlreadyInstalledAs => "Hachimi is already installed as {}",
+        Msg::ReplaceTarget => "Replace {}?",
+        Msg::DowngradeAnyway => "{}. Downgrade anyway?",
+        Msg::DeleteDataDir => "Do you also want to delete Hachimi's data directory?",
+        Msg::MultipleInstalls => "Multiple installations of Hachimi detected! \
+            The game will not work correctly unless only one of them is installed.\n\
+            Would you like to keep {} and uninstall the others?",
+        Msg::EnableRedirection => "DotLocal DLL redirection is not enabled. This is required for the specified install target.\n\
+            Would you like to enable it?",
+        Msg::RestartToApply => "Restart your computer to apply the changes.",
+        Msg::RegistryOpenFailed => "Failed to open IFEO registry key: {}"
+    }
+}
+
+fn tr_japanese(msg: Msg) -> &'static str {
+    match msg {
+        Msg::WarningTitle => "警告",
+        Msg::ErrorTitle => "エラー",
+        Msg::InstallTitle => "インストール",
+        Msg::UninstallTitle => "アンインストール",
+        Msg::SuccessTitle => "完了",
+        Msg::RedirectionEnabledTitle => "DLLリダイレクトを有効化しました",
+        Msg::Unknown => "不明",
+        Msg::None => "なし",
+        Msg::Corrupt => "破損",
+        Msg::Installed => "インストール済み: {}",
+        Msg::PackagedVersion => "同梱バージョン: {}",
+        Msg::InstallCompleted => "インストールが完了しました。",
+        Msg::UpdatedFrom => "{}から{}に更新しました。",
+        Msg::DeleteTarget => "{}を削除しますか？",
+        Msg::DeleteNotHachimi => "{}はHachimiではありません。それでも削除しますか？",
+        Msg::SteamManagedUninstall => "ゲームはSteamで管理されているため、ファイルが復元される可能性があります。\
+            アンインストールする前にSteamを終了してください。",
+        Msg::MsStoreProtected => "ゲームはMicrosoft Storeからインストールされています。フォルダーが保護されているため、\
+            フォルダーのアクセス許可を変更しないとHachimiをインストールできない場合があります。",
+        Msg::WriteProtected => "インストール先は書き込み保護されています。管理者としてインストーラーを再起動してください。",
+        Msg::DetectFailed => "ゲームのインストール先を検出できませんでした{}。手動で選択してください。",
+        Msg::UnsupportedGameVersion => "ゲームバージョン{}はこのバージョンのHachimiでテストされていません。\
+            Hachimiが更新されるまで正しく動作しない可能性があります。",
+        Msg::AnticheatDetected => "ゲームのフォルダーにアンチチートのコンポーネントが見つかりました（{}）。\
+            Hachimiを使用するとクラッシュやアカウント停止の原因になる可能性があります。自己責任で続行してください。",
+        Msg::AlreadyInstalledAs => "Hachimiは既に{}としてインストールされています",
+        Msg::ReplaceTarget => "{}を置き換えますか？",
+        Msg::DowngradeAnyway => "{}。それでもダウングレードしますか？",
+        Msg::DeleteDataDir => "Hachimiのデータフォルダーも削除しますか？",
+        Msg::MultipleInstalls => "Hachimiが複数インストールされています！\
+            1つだけがインストールされている状態でないと、ゲームは正しく動作しません。\n\
+            {}を残して他をアンインストールしますか？",
+        Msg::EnableRedirection => "DotLocal DLLリダイレクトが有効になっていません。指定したインストール先にはこの設定が必要です。\n\
+            有効にしますか？",
+        Msg::RestartToApply => "変更を適用するにはコンピューターを再起動してください。",
+        Msg::RegistryOpenFailed => "IFEOレジストリキーを開けませんでした: {}"
+    }
+}
+
+/// Translates `msg` and fills in its `{}` placeholders in order.
+pub fn tr_args(msg: Msg, args: &[&dyn std::fmt::Display]) -> String {
+    let mut args = args.iter();
+    let mut message = String::new();
+    for (i, part) in tr(msg).split("{}").enumerate() {
+        if i > 0 {
+            if let Some(arg) = args.next() {
+                message.push_str(&arg.to_string());
+            }
+        }
+        message.push_str(part);
+    }
+    message
+}
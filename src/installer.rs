@@ -1,79 +1,293 @@
-use std::{fs::File, io::Write, path::{Path, PathBuf}};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, path::{Path, PathBuf}, sync::Arc, time::SystemTime};
 
 use pelite::resources::version_info::Language;
+use log::{debug, info, warn};
 use registry::Hive;
+use sha2::{Digest, Sha256};
 use tinyjson::JsonValue;
-use windows::{core::{w, HSTRING}, Win32::{Foundation::HWND, UI::{Shell::{FOLDERID_RoamingAppData, SHGetKnownFolderPath, KF_FLAG_DEFAULT}, WindowsAndMessaging::{MessageBoxW, IDOK, MB_ICONINFORMATION, MB_ICONWARNING, MB_OK, MB_OKCANCEL}}}};
+use windows::{core::HSTRING, Win32::{Foundation::HWND, UI::{
+    Shell::ShellExecuteW,
+    WindowsAndMessaging::{MessageBoxW, IDOK, MB_ICONINFORMATION, MB_ICONWARNING, MB_OK, MB_OKCANCEL, SW_NORMAL}
+}}};
 
-use crate::utils::{self, get_system_directory};
+use crate::{
+    detect::{self, DetectError, DmmDetector, EnvDetector, InstallDirDetector, MsStoreDetector, ProcessDetector},
+    filesystem::{FileSystem, RealFileSystem},
+    i18n::{self, Lang, Msg},
+    utils::{self, get_system_directory}
+};
+#[cfg(feature = "download")]
+use crate::download;
 
+/// Product name in the version info of Hachimi DLLs.
+pub const HACHIMI_PRODUCT_NAME: &str = "Hachimi";
+
+#[derive(Clone)]
 pub struct Installer {
     pub install_dir: Option<PathBuf>,
     pub target: Target,
     pub custom_target: Option<String>,
     system_dir: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    version_info_cache: RefCell<HashMap<PathBuf, (SystemTime, TargetVersionInfo)>>,
+    variant: DllVariant,
+    pub prevent_downgrade: bool,
+    /// Move an existing non-Hachimi DLL aside instead of overwriting it
+    pub make_backup: bool,
+    /// Relative to the install dir, see `plugin_dir`
+    pub plugin_subdir: Option<PathBuf>,
+    /// Name that identifies Hachimi DLLs, see `is_hachimi`
+    pub product_name: String,
+    detectors: Arc<[Box<dyn InstallDirDetector>]>,
     pub hwnd: Option<HWND>
 }
 
 impl Installer {
     pub fn custom(install_dir: Option<PathBuf>, target: Target, custom_target: Option<String>) -> Installer {
+        Self::custom_with_detectors(install_dir, target, custom_target, detect::default_detectors().into())
+    }
+
+    fn custom_with_detectors(
+        install_dir: Option<PathBuf>, target: Target, custom_target: Option<String>,
+        detectors: Arc<[Box<dyn InstallDirDetector>]>
+    ) -> Installer {
         Installer {
-            install_dir: install_dir.or_else(Self::detect_install_dir),
+            install_dir: install_dir.or_else(|| Self::detect_install_dirs_with(&detectors).into_iter().next()),
             target,
             custom_target,
             system_dir: get_system_directory(),
+            fs: Arc::new(RealFileSystem),
+            version_info_cache: RefCell::default(),
+            variant: DllVariant::default(),
+            prevent_downgrade: false,
+            make_backup: true,
+            plugin_subdir: None,
+            product_name: HACHIMI_PRODUCT_NAME.to_owned(),
+            detectors,
             hwnd: None
         }
     }
 
-    fn detect_install_dir() -> Option<PathBuf> {
-        let app_data_dir_wstr = unsafe { SHGetKnownFolderPath(&FOLDERID_RoamingAppData, KF_FLAG_DEFAULT, None).ok()? };
-        let app_data_dir_str = unsafe { app_data_dir_wstr.to_string().ok()? };
-        let app_data_dir = Path::new(&app_data_dir_str);
-        let mut dmm_config_path = app_data_dir.join("dmmgameplayer5");
-        dmm_config_path.push("dmmgame.cnf");
+    pub fn builder() -> InstallerBuilder {
+        InstallerBuilder::new()
+    }
 
-        let config_str = std::fs::read_to_string(dmm_config_path).ok()?;
-        let JsonValue::Object(config) = config_str.parse().ok()? else {
-            return None;
-        };
-        let JsonValue::Array(config_contents) = &config["contents"] else {
-            return None;
-        };
-        for value in config_contents {
-            let JsonValue::Object(game) = value else {
-                return None;
-            };
+    /// A builder with all of the settings of this installer, except for the window handle.
+    pub fn to_builder(&self) -> InstallerBuilder {
+        InstallerBuilder {
+            install_dir: self.install_dir.clone(),
+            target: Some(self.target),
+            custom_target: self.custom_target.clone(),
+            variant: Some(self.variant),
+            prevent_downgrade: self.prevent_downgrade,
+            make_backup: Some(self.make_backup),
+            plugin_subdir: self.plugin_subdir.clone(),
+            product_name: Some(self.product_name.clone()),
+            fs: Some(self.fs.clone()),
+            detectors: Some(self.detectors.clone())
+        }
+    }
 
-            let JsonValue::String(product_id) = &game["productId"] else {
-                continue;
-            };
-            if product_id != "umamusume" {
-                continue;
-            }
+    pub fn with_filesystem(mut self, fs: Arc<dyn FileSystem>) -> Installer {
+        self.fs = fs;
+        self.refresh();
+        self
+    }
 
-            let JsonValue::Object(detail) = &game["detail"] else {
-                return None;
-            };
-            let JsonValue::String(path_str) = &detail["path"] else {
-                return None;
-            };
+    /// Detects the game's install directory without constructing an `Installer`.
+    ///
+    /// Tries, in order: the `HACHIMI_INSTALL_DIR` env var, DMM Game Player's config,
+    /// Microsoft Store packages and the running game process. Returns the first candidate found.
+    pub fn detect_install_dir() -> Option<PathBuf> {
+        Self::detect_install_dirs().into_iter().next()
+    }
+
+    pub fn detect_install_dirs() -> Vec<PathBuf> {
+        Self::detect_install_dirs_with(&detect::default_detectors())
+    }
 
-            let path = PathBuf::from(path_str);
-            return if path.is_dir() {
-                Some(path)
+    pub fn detect_install_dirs_with(detectors: &[Box<dyn InstallDirDetector>]) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for dir in detectors.iter().flat_map(|d| d.detect_all()) {
+            if !dirs.contains(&dir) {
+                debug!("Detected install dir candidate: {}", dir.display());
+                dirs.push(dir);
             }
-            else {
-                None
+        }
+        if dirs.is_empty() {
+            warn!("Failed to detect the install dir");
+        }
+        dirs
+    }
+
+    /// Like `detect_install_dirs`, but with the detectors that this installer was built with.
+    pub fn detected_install_dirs(&self) -> Vec<PathBuf> {
+        Self::detect_install_dirs_with(&self.detectors)
+    }
+
+    fn detect_install_dir_from_env() -> Option<PathBuf> {
+        EnvDetector.detect()
+    }
+
+    pub fn detect_install_dir_from_process() -> Option<PathBuf> {
+        ProcessDetector.detect()
+    }
+
+    /// Same order as `detect_install_dir`, but tells why detection failed.
+    pub fn detect_install_dir_result() -> Result<PathBuf, DetectError> {
+        if let Some(path) = Self::detect_install_dir_from_env() {
+            info!("Using install dir from HACHIMI_INSTALL_DIR: {}", path.display());
+            return Ok(path);
+        }
+
+        let dmm_res = DmmDetector.read_install_dirs()
+            .and_then(|dirs| dirs.into_iter().next().ok_or(DetectError::ResolveFailed));
+        match dmm_res {
+            Ok(path) => {
+                info!("Detected install dir from DMM Game Player: {}", path.display());
+                Ok(path)
+            },
+            Err(e) => {
+                debug!("DMM Game Player detection failed: {}", e);
+                if let Some(path) = MsStoreDetector.detect() {
+                    info!("Detected install dir from Microsoft Store packages: {}", path.display());
+                    return Ok(path);
+                }
+
+                // Report why DMM detection failed, since it's the most common install
+                let path = Self::detect_install_dir_from_process().ok_or(e)?;
+                info!("Detected install dir from the game process: {}", path.display());
+                Ok(path)
             }
         }
+    }
 
-        None
+    /// Whether the installer runs under Proton, going by Wine being present and Proton's
+    /// `STEAM_COMPAT_DATA_PATH` env var. The compatdata prefix isn't resolved: the install dir
+    /// is detected the same way as on Windows, or has to be set with `HACHIMI_INSTALL_DIR`.
+    pub fn is_proton_install(&self) -> bool {
+        utils::is_running_under_wine() && std::env::var_os("STEAM_COMPAT_DATA_PATH").is_some()
+    }
+
+    /// Which Steam package launched the installer under Proton, going by `FLATPAK_ID` and
+    /// whether `STEAM_COMPAT_DATA_PATH` is under ~/.var/app/com.valvesoftware.Steam. It's only
+    /// reported in the diagnostics, the Flatpak's data dir isn't searched for the game.
+    pub fn steam_flavor(&self) -> Option<SteamFlavor> {
+        if !self.is_proton_install() {
+            return None;
+        }
+
+        let compat_data_path = std::env::var("STEAM_COMPAT_DATA_PATH").unwrap_or_default();
+        if std::env::var("FLATPAK_ID").as_deref() == Ok(FLATPAK_STEAM_ID) ||
+            compat_data_path.contains(&format!("/.var/app/{}/", FLATPAK_STEAM_ID))
+        {
+            Some(SteamFlavor::Flatpak)
+        }
+        else {
+            Some(SteamFlavor::Native)
+        }
+    }
+
+    /// Microsoft Store games live in WindowsApps, which is protected by restrictive ACLs.
+    pub fn is_msstore_install(&self) -> bool {
+        let Some(install_dir) = self.resolved_install_dir() else {
+            return false;
+        };
+        install_dir.ancestors()
+            .filter_map(|p| p.file_name())
+            .any(|name| name.eq_ignore_ascii_case("WindowsApps"))
+    }
+
+    /// Heuristic for whether Steam manages the install dir, in which case Steam might restore
+    /// or remove files on its own (e.g. when verifying the game files).
+    pub fn warn_steam_managed(&self) -> bool {
+        self.detected_library_path().is_some()
+    }
+
+    /// The Steam library folder (the one containing `steamapps`) that the install dir is in.
+    pub fn detected_library_path(&self) -> Option<PathBuf> {
+        let install_dir = self.resolved_install_dir()?;
+        let steamapps_dir = install_dir.ancestors()
+            .find(|p| p.file_name().map(|name| name.eq_ignore_ascii_case("steamapps")).unwrap_or(false))?;
+        Some(steamapps_dir.parent()?.to_owned())
+    }
+
+    /// Sets the language of user-facing messages, including `Error`'s `Display` output.
+    pub fn set_language(lang: Lang) {
+        lang.set_current();
+    }
+
+    pub fn is_valid_install_dir(path: &Path) -> bool {
+        path.join("umamusume.exe").is_file()
+    }
+
+    /// Portable mode keeps the config next to the installer, enabled by placing a
+    /// portable.txt file beside it.
+    pub fn is_portable() -> bool {
+        utils::get_exe_dir().map(|dir| dir.join("portable.txt").is_file()).unwrap_or(false)
+    }
+
+    fn get_config_path() -> Option<PathBuf> {
+        if Self::is_portable() {
+            return Some(utils::get_exe_dir()?.join("config.json"));
+        }
+        Some(utils::get_app_data_dir()?.join("hachimi_installer").join("config.json"))
+    }
+
+    pub fn save_config(&self) -> Result<(), Error> {
+        let path = Self::get_config_path().ok_or(Error::NoConfigDir)?;
+        let config = InstallerConfig::from(self);
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, config.to_json().stringify().unwrap())?;
+        Ok(())
+    }
+
+    pub fn load_config() -> Option<Installer> {
+        let config_str = std::fs::read_to_string(Self::get_config_path()?).ok()?;
+        let mut config = InstallerConfig::from_json(&config_str.parse().ok()?)?;
+
+        // The env var override still takes precedence over the saved install dir
+        config.install_dir = Self::detect_install_dir_from_env().or(config.install_dir.filter(|p| p.is_dir()));
+
+        Some(config.into())
+    }
+
+    /// The install dir with symlinks and junctions resolved, so that files end up where
+    /// the game actually loads them from.
+    pub fn resolved_install_dir(&self) -> Option<PathBuf> {
+        let install_dir = self.install_dir.as_ref()?;
+        Some(
+            self.fs.canonicalize(install_dir)
+                .map(|p| utils::strip_extended_prefix(&p))
+                .unwrap_or_else(|_| install_dir.clone())
+        )
+    }
+
+    const DEFAULT_PLUGIN_SUBDIR: &str = "umamusume_Data\\Plugins\\x86_64";
+
+    /// Directory that the game loads native plugins from, where plugin shim targets find the
+    /// original plugin. Defaults to `umamusume_Data\Plugins\x86_64`, unless `plugin_subdir` is set.
+    pub fn plugin_dir(&self) -> Option<PathBuf> {
+        let install_dir = self.resolved_install_dir()?;
+        Some(match &self.plugin_subdir {
+            Some(subdir) => install_dir.join(subdir),
+            None => install_dir.join(Self::DEFAULT_PLUGIN_SUBDIR)
+        })
+    }
+
+    fn check_plugin_subdir(&self) -> Result<(), Error> {
+        match &self.plugin_subdir {
+            Some(subdir) if escapes_base_dir(subdir) => {
+                Err(Error::TargetOutsideInstallDir(subdir.display().to_string()))
+            },
+            _ => Ok(())
+        }
     }
 
     fn get_target_path_internal(&self, target: Target, p: impl AsRef<Path>) -> Option<PathBuf> {
         Some(match TargetType::from(target) {
-            TargetType::DotLocal => self.install_dir.as_ref()?.join("umamusume.exe.local").join(p),
+            TargetType::DotLocal => self.resolved_install_dir()?.join("umamusume.exe.local").join(p),
             TargetType::PluginShim => self.system_dir.join(p)
         })
     }
@@ -82,6 +296,18 @@ impl Installer {
         self.get_target_path_internal(target, target.dll_name())
     }
 
+    /// Paths of every target, with the custom target name applied to the current target.
+    pub fn target_paths(&self) -> Vec<(Target, PathBuf)> {
+        Target::VALUES.iter()
+            .filter_map(|t| Some((*t, if *t == self.target {
+                self.get_current_target_path()?
+            }
+            else {
+                self.get_target_path(*t)?
+            })))
+            .collect()
+    }
+
     pub fn get_current_target_path(&self) -> Option<PathBuf> {
         self.get_target_path_internal(self.target, if let Some(custom_target) = &self.custom_target {
             custom_target
@@ -91,22 +317,115 @@ impl Installer {
         })
     }
 
+    pub fn validate_custom_target(&self) -> Result<(), Error> {
+        let Some(custom_target) = &self.custom_target else {
+            return Ok(());
+        };
+        if !custom_target.to_ascii_lowercase().ends_with(".dll") {
+            return Err(Error::InvalidTarget(custom_target.clone()));
+        }
+
+        self.check_custom_target_dir()?;
+
+        // Make sure that we're not about to overwrite one of the game's own DLLs
+        let Some(install_dir) = self.resolved_install_dir() else {
+            return Ok(());
+        };
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        let game_dll_dirs = [install_dir.clone(), self.plugin_dir().ok_or(Error::NoInstallDir)?];
+        if game_dll_dirs.iter().any(|dir| path.parent() == Some(dir.as_path())) &&
+            self.fs.is_file(&path) &&
+            !self.read_version_info(&path).map(|v| self.is_hachimi(&v)).unwrap_or(false)
+        {
+            return Err(Error::CustomTargetIsGameDll(custom_target.clone()));
+        }
+
+        Ok(())
+    }
+
+    // Relative targets may point to a subdirectory, but must not escape the base dir
+    fn check_custom_target_dir(&self) -> Result<(), Error> {
+        match &self.custom_target {
+            Some(custom_target) if escapes_base_dir(Path::new(custom_target)) => {
+                Err(Error::TargetOutsideInstallDir(custom_target.clone()))
+            },
+            _ => Ok(())
+        }
+    }
+
+    pub fn is_hachimi(&self, version_info: &TargetVersionInfo) -> bool {
+        version_info.is_product(&self.product_name)
+    }
+
+    /// Drops the cached version info, forcing the target DLLs to be read again.
+    pub fn refresh(&self) {
+        self.version_info_cache.borrow_mut().clear();
+    }
+
+    // Cached by path and mtime, since reading the PE file is slow on network drives
+    fn read_version_info(&self, path: &Path) -> Option<TargetVersionInfo> {
+        let modified = self.fs.metadata(path).ok()?.modified;
+        if let Some(modified) = modified {
+            if let Some((cached_modified, version_info)) = self.version_info_cache.borrow().get(path) {
+                if *cached_modified == modified {
+                    return Some(version_info.clone());
+                }
+            }
+        }
+
+        let version_info = self.read_version_info_uncached(path)?;
+        if let Some(modified) = modified {
+            self.version_info_cache.borrow_mut().insert(path.to_owned(), (modified, version_info.clone()));
+        }
+        Some(version_info)
+    }
+
     const LANG_NEUTRAL_UNICODE: Language = Language { lang_id: 0x0000, charset_id: 0x04b0 };
-    pub fn get_target_version_info(&self, target: Target) -> Option<TargetVersionInfo> {
-        let path = self.get_target_path(target)?;
-        let map = pelite::FileMap::open(&path).ok()?;
+    fn read_version_info_uncached(&self, path: &Path) -> Option<TargetVersionInfo> {
+        let data = self.fs.read(path).ok()?;
 
         // File exists, so return empty version info if we can't read it
-        let Some(version_info) = utils::read_pe_version_info(map.as_ref()) else {
-            return Some(TargetVersionInfo::default());
-        };
+        Some(Self::parse_version_info(&data).unwrap_or_default())
+    }
 
+    fn parse_version_info(image: &[u8]) -> Option<TargetVersionInfo> {
+        let version_info = utils::read_pe_version_info(image)?;
         Some(TargetVersionInfo {
             name: version_info.value(Self::LANG_NEUTRAL_UNICODE, "ProductName"),
-            version: version_info.value(Self::LANG_NEUTRAL_UNICODE, "ProductVersion")
+            version: version_info.value(Self::LANG_NEUTRAL_UNICODE, "ProductVersion"),
+            file_version: version_info.value(Self::LANG_NEUTRAL_UNICODE, "FileVersion"),
+            company_name: version_info.value(Self::LANG_NEUTRAL_UNICODE, "CompanyName"),
+            description: version_info.value(Self::LANG_NEUTRAL_UNICODE, "FileDescription")
         })
     }
 
+    pub fn get_target_version_info(&self, target: Target) -> Option<TargetVersionInfo> {
+        self.read_version_info(&self.get_target_path(target)?)
+    }
+
+    pub fn get_custom_target_version_info(&self) -> Option<TargetVersionInfo> {
+        self.read_version_info(&self.get_current_target_path()?)
+    }
+
+    /// Like `get_target_version_info`, but tells a missing target apart from one that can't be read.
+    /// Returns `None` if the target doesn't exist, `Some(Err(...))` if it exists but is unreadable or
+    /// not a valid PE file. Valid DLLs without version info still return empty version info.
+    pub fn get_target_version_info_result(&self, target: Target) -> Option<Result<TargetVersionInfo, Error>> {
+        let path = self.get_target_path(target)?;
+        if !self.fs.is_file(&path) {
+            return None;
+        }
+
+        let data = match self.fs.read(&path) {
+            Ok(data) => data,
+            Err(e) => return Some(Err(Error::IoError(e)))
+        };
+        if pelite::PeFile::from_bytes(&data).is_err() {
+            return Some(Err(Error::InvalidDll));
+        }
+        Some(Ok(Self::parse_version_info(&data).unwrap_or_default()))
+    }
+
     pub fn get_target_display_label(&self, target: Target) -> String {
         if let Some(version_info) = self.get_target_version_info(target) {
             version_info.get_display_label(target)
@@ -116,174 +435,1341 @@ impl Installer {
         }
     }
 
+    /// Uses `info` if the caller has already read it, otherwise falls back to reading the target.
+    pub fn target_label_cached(&self, target: Target, info: Option<&TargetVersionInfo>) -> String {
+        match info {
+            Some(version_info) => version_info.get_display_label(target),
+            None => self.get_target_display_label(target)
+        }
+    }
+
+    /// A directory at the target path doesn't count as installed, see `check_target_path`.
     pub fn is_current_target_installed(&self) -> bool {
         let Some(path) = self.get_current_target_path() else {
             return false;
         };
 
-        let Ok(metadata) = std::fs::metadata(&path) else {
+        self.fs.is_file(&path)
+    }
+
+    /// Checked before anything is written to or removed from the target path.
+    fn check_target_path(&self, path: &Path) -> Result<(), Error> {
+        self.check_custom_target_dir()?;
+        self.check_plugin_subdir()?;
+        if self.fs.is_dir(path) {
+            return Err(Error::TargetIsDirectory(path.to_owned()));
+        }
+        Ok(())
+    }
+
+    pub fn install_dir_info(&self) -> Option<DirInfo> {
+        let install_dir = self.resolved_install_dir()?;
+        let (available_space, total_space) = utils::get_disk_space(&install_dir)?;
+        Some(DirInfo {
+            total_space,
+            available_space,
+            is_removable: utils::is_removable_drive(&install_dir)
+        })
+    }
+
+    pub fn can_write_install_dir(&self) -> bool {
+        let Some(install_dir) = &self.install_dir else {
             return false;
         };
 
-        metadata.is_file()
+        let test_path = install_dir.join(".hachimi-write-test");
+        if self.fs.write(&test_path, &[]).is_err() {
+            return false;
+        }
+        _ = self.fs.remove_file(&test_path);
+        true
     }
 
-    pub fn get_hachimi_installed_target(&self) -> Option<Target> {
-        for target in Target::VALUES {
-            if let Some(version_info) = self.get_target_version_info(*target) {
-                if version_info.is_hachimi() {
-                    return Some(*target);
-                }
-            }
-        }
-        None
+    pub fn get_hachimi_dir(&self) -> Option<PathBuf> {
+        Some(self.resolved_install_dir()?.join("hachimi"))
     }
 
-    pub fn pre_install(&self) -> Result<(), Error> {
-        if TargetType::from(self.target) == TargetType::PluginShim {
-            let dest_dll = self.get_dest_plugin_path().ok_or(Error::NoInstallDir)?;
-            let src_dll = self.get_src_plugin_path().ok_or(Error::NoInstallDir)?;
+    /// Path of Hachimi's own config file, not to be confused with the installer's config.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        Some(self.get_hachimi_dir()?.join("config.json"))
+    }
 
-            if !dest_dll.exists() && !src_dll.exists() {
-                return Err(Error::CannotFindTarget);
-            }
+    /// Writes an empty config for Hachimi to fill in with its defaults. Existing configs
+    /// are never overwritten; returns whether the file was written.
+    pub fn install_default_config(&self) -> Result<bool, Error> {
+        let path = self.config_path().ok_or(Error::NoInstallDir)?;
+        if self.fs.exists(&path) {
+            return Ok(false);
         }
 
-        Ok(())
+        self.fs.create_dir_all(path.parent().unwrap())?;
+        self.fs.write(&path, b"{}")?;
+        Ok(true)
     }
 
-    pub fn install(&self) -> Result<(), Error> {
-        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
-        std::fs::create_dir_all(path.parent().unwrap())?;
-        let mut file = File::create(&path)?;
+    pub fn get_game_exe_path(&self) -> Option<PathBuf> {
+        Some(self.resolved_install_dir()?.join("umamusume.exe"))
+    }
 
-        #[cfg(feature = "compress_dll")]
-        file.write(&include_bytes_zstd!("hachimi.dll", 19))?;
+    const DMM_LAUNCH_URL: &str = "dmmgameplayer://play/GCL/umamusume/cl/win";
 
-        #[cfg(not(feature = "compress_dll"))]
-        file.write(include_bytes!("../hachimi.dll"))?;
+    fn is_dmm_install(&self) -> bool {
+        let Some(install_dir) = &self.install_dir else {
+            return false;
+        };
+        DmmDetector.read_install_dirs().map(|dirs| dirs.contains(install_dir)).unwrap_or(false)
+    }
 
-        Ok(())
+    /// Launches the game through DMM Game Player if it manages the install dir,
+    /// otherwise runs the game executable directly.
+    pub fn launch_game(&self) -> Result<(), Error> {
+        if self.is_dmm_install() && Self::shell_execute(Self::DMM_LAUNCH_URL, "", None) {
+            return Ok(());
+        }
+        self.launch_game_exe("")
     }
 
-    pub fn post_install(&self) -> Result<(), Error> {
-        match TargetType::from(self.target) {
-            TargetType::DotLocal => {
-                match Hive::LocalMachine.open(
-                    r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options",
-                    registry::Security::Read | registry::Security::SetValue
-                ) {
-                    Ok(regkey) => {
-                        if regkey.value("DevOverrideEnable").ok()
-                            .map(|v| !matches!(v, registry::Data::U32(..)))
-                            .unwrap_or(true)
-                        {
-                            let res = unsafe {
-                                MessageBoxW(
-                                    self.hwnd.as_ref(),
-                                    w!("DotLocal DLL redirection is not enabled. This is required for the specified install target.\n\
-                                        Would you like to enable it?"),
-                                    w!("Install"),
-                                    MB_ICONINFORMATION | MB_OKCANCEL
-                                )
-                            };
-                            if res == IDOK {
-                                regkey.set_value("DevOverrideEnable", &registry::Data::U32(1))?;
-                                unsafe {
-                                    MessageBoxW(
-                                        self.hwnd.as_ref(),
-                                        w!("Restart your computer to apply the changes."),
-                                        w!("DLL redirection enabled"),
-                                        MB_ICONINFORMATION | MB_OK
-                                    );
-                                }
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        unsafe { MessageBoxW(
-                            self.hwnd.as_ref(),
-                            &HSTRING::from(format!("Failed to open IFEO registry key: {}", e)),
-                            w!("Warning"),
-                            MB_OK | MB_ICONWARNING
-                        )};
-                    }
-                }
-            },
-            TargetType::PluginShim => {
-                let dest_dll = self.get_dest_plugin_path().ok_or(Error::NoInstallDir)?;
-                let src_dll = self.get_src_plugin_path().ok_or(Error::NoInstallDir)?;
+    pub fn launch_game_exe(&self, args: &str) -> Result<(), Error> {
+        let exe_path = self.get_game_exe_path().ok_or(Error::NoInstallDir)?;
+        if !self.fs.is_file(&exe_path) {
+            return Err(Error::CannotLaunchGame);
+        }
 
-                if src_dll.exists() {
-                    std::fs::create_dir_all(dest_dll.parent().unwrap())?;
-                    std::fs::copy(&src_dll, &dest_dll)?;
-                    std::fs::remove_file(&src_dll)?;
-                }
-            }
+        if Self::shell_execute(exe_path.to_str().unwrap(), args, self.install_dir.as_deref()) {
+            Ok(())
+        }
+        else {
+            Err(Error::CannotLaunchGame)
         }
+    }
 
-        Ok(())
+    fn shell_execute(file: &str, args: &str, dir: Option<&Path>) -> bool {
+        let dir = dir.map(|p| HSTRING::from(p.to_str().unwrap())).unwrap_or_default();
+        // Values above 32 indicate success
+        let res = unsafe {
+            ShellExecuteW(None, None, &HSTRING::from(file), &HSTRING::from(args), &dir, SW_NORMAL)
+        };
+        res.0 as isize > 32
     }
 
-    pub fn uninstall(&self) -> Result<(), Error> {
-        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
-        std::fs::remove_file(&path)?;
+    pub fn game_version(&self) -> Option<String> {
+        let exe = self.fs.read(&self.get_game_exe_path()?).ok()?;
+        let version_info = utils::read_pe_version_info(&exe)?;
+        version_info.value(Self::LANG_NEUTRAL_UNICODE, "ProductVersion")
+            .filter(|v| !v.is_empty())
+            .or_else(|| version_info.value(Self::LANG_NEUTRAL_UNICODE, "FileVersion"))
+    }
 
-        match TargetType::from(self.target) {
-            TargetType::DotLocal => {
-                // Only remove if its empty
-                _ = std::fs::remove_dir(path.parent().unwrap());
-            },
-            TargetType::PluginShim => {
-                let dest_dll = self.get_dest_plugin_path().ok_or(Error::NoInstallDir)?;
-                let src_dll = self.get_src_plugin_path().ok_or(Error::NoInstallDir)?;
-                if !src_dll.exists() {
-                    std::fs::copy(&dest_dll, &src_dll)?;
-                    std::fs::remove_file(&dest_dll)?;
-                }
-            }
-        }
+    /// Short hex ID of the install, made from the resolved install dir and the game version.
+    /// Stable across runs, but changes when the game is updated or moved.
+    pub fn install_fingerprint(&self) -> Option<String> {
+        // Paths are case insensitive, so normalize the case to not depend on how it was entered
+        let install_dir = self.resolved_install_dir()?.to_string_lossy().to_lowercase();
+        let game_version = self.game_version()?;
 
-        Ok(())
+        let mut hasher = Sha256::new();
+        hasher.update(install_dir.as_bytes());
+        hasher.update([0]);
+        hasher.update(game_version.as_bytes());
+        Some(hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect())
     }
 
-    pub fn get_dest_plugin_path(&self) -> Option<PathBuf> {
-        Some(self.install_dir.as_ref()?.join(format!("hachimi\\{}", self.target.dll_name())))
+    // Semver requirement set at build time; any game version is considered supported if it's unset
+    const SUPPORTED_GAME_VERSIONS: Option<&str> = option_env!("HACHIMI_SUPPORTED_GAME_VERSIONS");
+
+    /// Returns `None` if there's no known-good range or the game version can't be determined.
+    pub fn is_game_version_supported(&self) -> Option<bool> {
+        let req = semver::VersionReq::parse(Self::SUPPORTED_GAME_VERSIONS?).ok()?;
+        let version = utils::parse_version(&self.game_version()?)?;
+        Some(req.matches(&version))
     }
 
-    pub fn get_src_plugin_path(&self) -> Option<PathBuf> {
-        Some(self.install_dir.as_ref()?.join(format!("umamusume_Data\\Plugins\\x86_64\\{}", self.target.dll_name())))
+    // Relative to the install dir
+    const KNOWN_ANTICHEAT_FILES: &[&str] = &[
+        "EasyAntiCheat_x64.dll",
+        "start_protected_game.exe",
+        "EasyAntiCheat\\EasyAntiCheat_EOS_Setup.exe",
+        "BattlEye\\BEClient_x64.dll",
+        "BattlEye\\BEService_x64.exe",
+        "GameGuard\\GameGuard.des",
+        "XIGNCODE\\x3.xem"
+    ];
+
+    pub fn detect_anticheat(&self) -> Option<String> {
+        let install_dir = self.resolved_install_dir()?;
+        Self::KNOWN_ANTICHEAT_FILES.iter()
+            .find(|name| self.fs.is_file(&install_dir.join(name)))
+            .map(|name| name.to_string())
     }
-}
 
-impl Default for Installer {
-    fn default() -> Installer {
-        Installer {
-            install_dir: Self::detect_install_dir(),
-            target: Target::default(),
-            custom_target: None,
-            system_dir: get_system_directory(),
-            hwnd: None
+    // Only shown when there's a window to own the message box, so that unattended installs
+    // (the CLI, `watch`) never block on it. Those can call `detect_anticheat` themselves.
+    fn warn_anticheat(&self) {
+        if self.hwnd.is_none() {
+            return;
+        }
+        let Some(anticheat) = self.detect_anticheat() else {
+            return;
+        };
+        warn!("Anti-cheat component found: {}", anticheat);
+
+        unsafe {
+            MessageBoxW(
+                self.hwnd.as_ref(),
+                &HSTRING::from(i18n::tr_args(Msg::AnticheatDetected, &[&anticheat])),
+                &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                MB_ICONWARNING | MB_OK
+            );
         }
     }
-}
 
-#[derive(Clone, Copy, Eq, PartialEq)]
-pub enum Target {
-    UnityPlayer,
-    CriManaVpx
-}
+    // Same as `warn_anticheat`, see `is_game_version_supported` for unattended installs
+    fn warn_unsupported_game_version(&self) {
+        if self.hwnd.is_none() || self.is_game_version_supported() != Some(false) {
+            return;
+        }
 
-impl Target {
-    pub const VALUES: &[Self] = &[
-        Self::UnityPlayer,
-        Self::CriManaVpx
+        unsafe {
+            MessageBoxW(
+                self.hwnd.as_ref(),
+                &HSTRING::from(i18n::tr_args(Msg::UnsupportedGameVersion, &[&self.game_version().unwrap_or_default()])),
+                &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                MB_ICONWARNING | MB_OK
+            );
+        }
+    }
+
+    pub fn recommended_target(&self) -> Option<Target> {
+        // Pick the first target that the game actually imports
+        Some(self.imported_targets()?.first().copied().unwrap_or_default())
+    }
+
+    /// Targets that the game executable imports directly.
+    pub fn loadable_targets(&self) -> Vec<Target> {
+        self.imported_targets().unwrap_or_default()
+    }
+
+    fn imported_targets(&self) -> Option<Vec<Target>> {
+        let exe = self.fs.read(&self.get_game_exe_path()?).ok()?;
+        let imported_dlls = utils::read_pe_import_names(&exe)?;
+
+        Some(
+            Target::VALUES.iter()
+                .filter(|t| imported_dlls.iter().any(|name| name.eq_ignore_ascii_case(t.dll_name())))
+                .copied()
+                .collect()
+        )
+    }
+
+    pub fn all_hachimi_targets(&self) -> Vec<Target> {
+        self.installed_targets()
+            .into_iter()
+            .filter(|(_, version_info)| self.is_hachimi(version_info))
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    pub fn cleanup_duplicate_installs(&self, keep: Target) -> Result<(), Error> {
+        for target in self.all_hachimi_targets() {
+            if target == keep {
+                continue;
+            }
+
+            let path = self.get_target_path(target).ok_or(Error::NoInstallDir)?;
+            self.uninstall_from(target, &utils::to_extended_path(&path), true).map_err(|e| e.with_path(&path))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_hachimi_installed_target(&self) -> Option<Target> {
+        for target in Target::VALUES {
+            if let Some(version_info) = self.get_target_version_info(*target) {
+                if self.is_hachimi(&version_info) {
+                    return Some(*target);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn installed_targets(&self) -> Vec<(Target, TargetVersionInfo)> {
+        Target::VALUES.iter()
+            .filter_map(|t| Some((*t, self.get_target_version_info(*t)?)))
+            .collect()
+    }
+
+    pub fn detect_conflicts(&self) -> Vec<(Target, TargetVersionInfo)> {
+        self.installed_targets()
+            .into_iter()
+            .filter(|(_, version_info)| !self.is_hachimi(version_info))
+            .collect()
+    }
+
+    pub fn status(&self) -> InstallStatus {
+        let hachimi_target = self.get_hachimi_installed_target();
+        InstallStatus {
+            install_dir_found: self.install_dir.is_some(),
+            hachimi_target,
+            installed_version: hachimi_target
+                .and_then(|t| self.get_target_version_info(t))
+                .and_then(|v| v.get_version().map(|v| v.to_owned())),
+            current_target_occupied: self.is_current_target_installed()
+        }
+    }
+
+    pub fn diagnostics(&self) -> String {
+        let mut report = String::new();
+        let mut line = |s: String| {
+            report.push_str(&s);
+            report.push_str("\r\n");
+        };
+
+        line(format!("Installer version: {}", env!("CARGO_PKG_VERSION")));
+        line(format!("Packaged Hachimi version: {}", env!("HACHIMI_VERSION")));
+        line(format!(
+            "OS: {} ({}){}",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            if utils::is_running_under_wine() { ", Wine" } else { "" }
+        ));
+        line(format!("Steam: {}", self.steam_flavor().map(|f| f.to_string()).unwrap_or_else(|| "None".to_owned())));
+        line(format!(
+            "Install dir: {}",
+            self.install_dir.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "None".to_owned())
+        ));
+        line(format!(
+            "Steam library: {}",
+            self.detected_library_path().map(|p| p.display().to_string()).unwrap_or_else(|| "None".to_owned())
+        ));
+        line(format!("Game arch: {}", self.game_arch().map(|a| a.to_string()).unwrap_or_else(|| "Unknown".to_owned())));
+        line(format!(
+            "Running game arch: {}",
+            self.running_game_arch().map(|a| a.to_string()).unwrap_or_else(|| "None".to_owned())
+        ));
+        line(format!("Game running: {}", Self::is_game_running()));
+        line(format!("Selected target: {}", self.custom_target.as_deref().unwrap_or(self.target.dll_name())));
+        line(format!(
+            "Hachimi target: {}",
+            self.get_hachimi_installed_target().map(|t| t.dll_name()).unwrap_or("None")
+        ));
+
+        line("Targets:".to_owned());
+        for target in Target::VALUES {
+            let state = match self.get_target_version_info(*target) {
+                Some(info) => format!(
+                    "{} {} ({})",
+                    info.name.as_deref().unwrap_or("Unknown"),
+                    info.get_version().unwrap_or("Unknown"),
+                    if self.is_hachimi(&info) { "Hachimi" } else { "not Hachimi" }
+                ),
+                None => "Not present".to_owned()
+            };
+            line(format!("  {}: {}", target.dll_name(), state));
+        }
+
+        report
+    }
+
+    pub fn reveal_install_dir(&self) -> Result<(), Error> {
+        let install_dir = self.install_dir.as_ref().ok_or(Error::NoInstallDir)?;
+        // Explorer exits with a non-zero code even on success, so only a failed spawn is an error
+        std::process::Command::new("explorer").arg(install_dir).spawn()?;
+        Ok(())
+    }
+
+    pub fn write_diagnostics(&self, path: &Path) -> Result<(), Error> {
+        self.fs.write(path, self.diagnostics().as_bytes())?;
+        Ok(())
+    }
+
+    /// Compares the installed DLL's hash with the packaged one, since rebuilds don't always
+    /// bump the version. Returns `None` if there's nothing installed at the current target.
+    pub fn installed_matches_embedded(&self) -> Option<bool> {
+        let data = self.fs.read(&self.get_current_target_path()?).ok()?;
+        let installed_hash: [u8; 32] = Sha256::digest(&data).into();
+        Some(installed_hash == self.variant_dll_hash().ok()?)
+    }
+
+    pub fn verify_installation(&self) -> InstallHealth {
+        let mut problems = Vec::new();
+        let Some(path) = self.get_current_target_path() else {
+            return InstallHealth { problems: vec![HealthProblem::NoInstallDir] };
+        };
+
+        match self.fs.read(&path) {
+            Ok(data) => if pelite::PeFile::from_bytes(&data).is_err() {
+                problems.push(HealthProblem::InvalidDll);
+            }
+            else if !self.read_version_info(&path).map(|v| self.is_hachimi(&v)).unwrap_or(false) {
+                problems.push(HealthProblem::NotHachimi);
+            }
+            else if self.variant_dll_hash().ok() != Some(<[u8; 32]>::from(Sha256::digest(&data))) {
+                // A newer version than the packaged one is fine too
+                match (self.read_version_info(&path), self.variant_version_info()) {
+                    (Some(installed), Some(embedded))
+                        if compare_versions(&installed, &embedded) == Some(std::cmp::Ordering::Greater) => (),
+                    _ => problems.push(HealthProblem::HashMismatch)
+                }
+            },
+            Err(_) => problems.push(HealthProblem::Missing)
+        }
+
+        let hachimi_targets = self.all_hachimi_targets();
+        if hachimi_targets.len() > 1 {
+            problems.push(HealthProblem::Duplicated(hachimi_targets));
+        }
+
+        InstallHealth { problems }
+    }
+
+    pub fn is_game_running() -> bool {
+        utils::is_game_running()
+    }
+
+    pub fn pre_install(&self) -> Result<(), Error> {
+        if TargetType::from(self.target) == TargetType::PluginShim {
+            self.check_plugin_subdir()?;
+            let dest_dll = self.get_dest_plugin_path().ok_or(Error::NoInstallDir)?;
+            let src_dll = self.get_src_plugin_path().ok_or(Error::NoInstallDir)?;
+
+            if !self.fs.exists(&dest_dll) && !self.fs.exists(&src_dll) {
+                return Err(Error::CannotFindTarget);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn variant(&self) -> DllVariant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: DllVariant) {
+        self.variant = variant;
+    }
+
+    pub fn install(&self) -> Result<InstallOutcome, Error> {
+        self.install_with_progress(|_, _| {})
+    }
+
+    /// The callback receives the number of bytes written so far and the total, if known.
+    pub fn install_with_progress(&self, progress: impl FnMut(u64, Option<u64>)) -> Result<InstallOutcome, Error> {
+        self.install_embedded(self.prevent_downgrade, progress)
+    }
+
+    /// Installs even if it would downgrade Hachimi, for intentional rollbacks.
+    pub fn install_force(&self) -> Result<InstallOutcome, Error> {
+        self.install_embedded(false, |_, _| {})
+    }
+
+    fn install_embedded(
+        &self, prevent_downgrade: bool, mut progress: impl FnMut(u64, Option<u64>)
+    ) -> Result<InstallOutcome, Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        let dll = Self::get_variant_dll(self.variant)?;
+        info!("Installing the packaged DLL ({:?}) to {}", self.variant, path.display());
+        Self::verify_dll_image(&dll)?;
+        if prevent_downgrade {
+            self.check_downgrade(&dll)?;
+        }
+        self.check_arch(&dll)?;
+        self.warn_unsupported_game_version();
+        self.warn_anticheat();
+        self.install_to(&utils::to_extended_path(&path), &dll, &mut progress).map_err(|e| e.with_path(&path))
+    }
+
+    /// Runs `install` on tokio's blocking thread pool. HWND isn't Send, so the installer is
+    /// rebuilt on the worker thread and any message boxes won't have an owner window.
+    ///
+    /// The returned future doesn't borrow `self`, since `Installer` isn't Sync.
+    ///
+    /// The future must be polled within a Tokio runtime, otherwise it panics. GUI event loops that
+    /// don't run on Tokio should call `install` from their own worker thread instead.
+    #[cfg(feature = "async")]
+    pub fn install_async(&self) -> impl std::future::Future<Output = Result<InstallOutcome, Error>> + Send {
+        let builder = self.to_builder();
+        async move {
+            tokio::task::spawn_blocking(move || builder.build().install())
+                .await
+                .map_err(|e| Error::IoError(std::io::Error::other(e)))?
+        }
+    }
+
+    /// Runs `install_from_release` on tokio's blocking thread pool, with the same caveats as
+    /// `install_async`. The download itself still uses the blocking reqwest client.
+    #[cfg(all(feature = "async", feature = "download"))]
+    pub fn install_from_release_spawn_blocking(
+        &self, tag: Option<String>
+    ) -> impl std::future::Future<Output = Result<InstallOutcome, Error>> + Send {
+        let builder = self.to_builder();
+        async move {
+            tokio::task::spawn_blocking(move || builder.build().install_from_release(tag.as_deref()))
+                .await
+                .map_err(|e| Error::IoError(std::io::Error::other(e)))?
+        }
+    }
+
+    /// Path of a hachimi.dll placed next to the installer executable, if there is one.
+    pub fn colocated_dll_path() -> Option<PathBuf> {
+        let path = utils::get_exe_dir()?.join("hachimi.dll");
+        if path.is_file() {
+            Some(path)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Installs a loose DLL instead of the packaged one, e.g. one shipped next to the installer.
+    pub fn install_from_file(&self, dll_path: &Path) -> Result<InstallOutcome, Error> {
+        let dll = self.fs.read(dll_path)?;
+        self.install_external_dll(&dll, &mut |_, _| {})
+    }
+
+    // DLLs that didn't come with the installer might not be Hachimi at all
+    fn install_external_dll(&self, dll: &[u8], progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<InstallOutcome, Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        Self::verify_dll_image(dll)?;
+        if !Self::parse_version_info(dll).map(|v| self.is_hachimi(&v)).unwrap_or(false) {
+            return Err(Error::NotHachimi);
+        }
+        if self.prevent_downgrade {
+            self.check_downgrade(dll)?;
+        }
+        self.check_arch(dll)?;
+
+        self.install_to(&utils::to_extended_path(&path), dll, progress).map_err(|e| e.with_path(&path))
+    }
+
+    #[cfg(feature = "download")]
+    pub fn install_from_release(&self, tag: Option<&str>) -> Result<InstallOutcome, Error> {
+        self.install_from_release_with_retry(tag, Self::DEFAULT_DOWNLOAD_ATTEMPTS)
+    }
+
+    #[cfg(feature = "download")]
+    pub const DEFAULT_DOWNLOAD_ATTEMPTS: u32 = 3;
+
+    /// Falls back to the packaged DLL if GitHub can't be reached after all attempts. A missing
+    /// release or asset and HTTP errors are returned instead.
+    #[cfg(feature = "download")]
+    pub fn install_from_release_with_retry(&self, tag: Option<&str>, attempts: u32) -> Result<InstallOutcome, Error> {
+        self.install_from_release_with_progress(tag, attempts, |_, _| {})
+    }
+
+    /// Reports the download progress first, then the write progress.
+    #[cfg(feature = "download")]
+    pub fn install_from_release_with_progress(
+        &self, tag: Option<&str>, attempts: u32, mut progress: impl FnMut(u64, Option<u64>)
+    ) -> Result<InstallOutcome, Error> {
+        let dll = match download::download_release_dll_with_retry(tag, attempts, &mut progress) {
+            Ok(dll) => dll,
+            Err(e) if download::is_transient_error(&e) => {
+                let previous = match self.install_with_progress(&mut progress)? {
+                    InstallOutcome::Overwritten { previous } => previous,
+                    _ => None
+                };
+                return Ok(InstallOutcome::EmbeddedFallback { previous, error: e });
+            },
+            Err(e) => return Err(e)
+        };
+
+        self.install_external_dll(&dll, &mut progress)
+    }
+
+    /// Blocks and reinstalls Hachimi whenever the current target is removed or replaced
+    /// by something else, e.g. by a game update. `callback` is called after each reinstall.
+    #[cfg(feature = "watch")]
+    pub fn watch(&self, callback: impl Fn()) -> Result<(), Error> {
+        use notify::{RecursiveMode, Watcher};
+
+        let install_dir = self.resolved_install_dir().ok_or(Error::NoInstallDir)?;
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&install_dir, RecursiveMode::Recursive)?;
+        // Plugin shims live in the system dir
+        let target_dir = path.parent().unwrap();
+        if !target_dir.starts_with(&install_dir) {
+            watcher.watch(target_dir, RecursiveMode::NonRecursive)?;
+        }
+
+        for res in rx {
+            let event = res?;
+            if !event.paths.iter().any(|p| p == &path) {
+                continue;
+            }
+
+            // Our own install also triggers events, but the target is Hachimi by then
+            if self.read_version_info(&path).map(|v| self.is_hachimi(&v)).unwrap_or(false) {
+                continue;
+            }
+            if self.install().is_ok() {
+                callback();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Architecture of the running game process, if it was launched from the install dir.
+    pub fn running_game_arch(&self) -> Option<Arch> {
+        let process_dir = utils::get_game_process_path()?.parent()?.to_owned();
+        if utils::strip_extended_prefix(&process_dir) != self.resolved_install_dir()? {
+            return None;
+        }
+        Arch::from_machine(utils::get_game_process_machine()?)
+    }
+
+    pub fn game_arch(&self) -> Option<Arch> {
+        let exe = self.fs.read(&self.get_game_exe_path()?).ok()?;
+        Arch::from_machine(utils::read_pe_machine(&exe)?)
+    }
+
+    pub fn embedded_arch() -> Option<Arch> {
+        Arch::from_machine(utils::read_pe_machine(&Self::get_embedded_dll().ok()?)?)
+    }
+
+    fn check_downgrade(&self, dll: &[u8]) -> Result<(), Error> {
+        let Some(installed) = self.get_custom_target_version_info().filter(|v| self.is_hachimi(v)) else {
+            return Ok(());
+        };
+        let Some(embedded) = Self::parse_version_info(dll) else {
+            return Ok(());
+        };
+
+        if compare_versions(&installed, &embedded) == Some(std::cmp::Ordering::Greater) {
+            return Err(Error::WouldDowngrade {
+                installed: installed.get_version().unwrap_or_default().to_owned(),
+                embedded: embedded.get_version().unwrap_or_default().to_owned()
+            });
+        }
+        Ok(())
+    }
+
+    fn check_arch(&self, dll: &[u8]) -> Result<(), Error> {
+        // The exe on disk might be a launcher stub, so prefer the actual process if it's running
+        let Some(game_arch) = self.running_game_arch().or_else(|| self.game_arch()) else {
+            return Ok(());
+        };
+        let Some(dll_arch) = utils::read_pe_machine(dll).and_then(Arch::from_machine) else {
+            return Ok(());
+        };
+
+        if game_arch != dll_arch {
+            warn!("Architecture mismatch: game is {}, DLL is {}", game_arch, dll_arch);
+            return Err(Error::ArchMismatch { game: game_arch, dll: dll_arch });
+        }
+        Ok(())
+    }
+
+    fn install_to(&self, path: &Path, dll: &[u8], progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<InstallOutcome, Error> {
+        if Self::is_game_running() {
+            return Err(Error::GameRunning);
+        }
+        self.check_target_path(path)?;
+
+        let outcome = if self.fs.is_file(path) {
+            InstallOutcome::Overwritten { previous: self.read_version_info(path) }
+        }
+        else {
+            InstallOutcome::Fresh
+        };
+
+        self.fs.create_dir_all(path.parent().unwrap())?;
+        if let Some((available, _)) = utils::get_disk_space(path.parent().unwrap()) {
+            if available < dll.len() as u64 {
+                return Err(Error::InsufficientSpace { required: dll.len() as u64, available });
+            }
+        }
+
+        // Write to a temp file first so that the target is never left half-written
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let total = dll.len() as u64;
+        let res = self.fs.write_with_progress(&tmp_path, dll, &mut |written| progress(written, Some(total)))
+            .map_err(Error::from)
+            .and_then(|_| self.verify_dll(&tmp_path, dll))
+            .and_then(|_| if self.make_backup { self.backup_target(path) } else { Ok(false) })
+            .and_then(|backed_up| self.fs.rename(&tmp_path, path).map_err(|e| {
+                // Put the original back, so that the target is never left empty
+                if backed_up {
+                    _ = self.restore_target_backup(path);
+                }
+                Error::from(e)
+            }));
+        if res.is_err() {
+            _ = self.fs.remove_file(&tmp_path);
+        }
+        if let Err(e) = &res {
+            warn!("Failed to install to {}: {}", path.display(), e);
+        }
+        res?;
+
+        info!("Installed to {}: {:?}", path.display(), outcome);
+        Ok(outcome)
+    }
+
+    #[cfg(feature = "compress_dll")]
+    fn get_embedded_dll() -> Result<Cow<'static, [u8]>, Error> {
+        static COMPRESSED_DLL: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/hachimi.dll.zst"));
+        let dll = zstd::decode_all(COMPRESSED_DLL).map_err(|_| Error::Decompression)?;
+        if Some(dll.len()) != Self::expected_dll_size() {
+            return Err(Error::Decompression);
+        }
+        Ok(Cow::Owned(dll))
+    }
+
+    #[cfg(feature = "compress_dll")]
+    fn expected_dll_size() -> Option<usize> {
+        env!("HACHIMI_DLL_SIZE").parse().ok()
+    }
+
+    #[cfg(not(feature = "compress_dll"))]
+    fn get_embedded_dll() -> Result<Cow<'static, [u8]>, Error> {
+        Ok(Cow::Borrowed(include_bytes!("../hachimi.dll")))
+    }
+
+    // The debug build is only used for testing, so it's never compressed
+    fn get_variant_dll(variant: DllVariant) -> Result<Cow<'static, [u8]>, Error> {
+        match variant {
+            DllVariant::Release => Self::get_embedded_dll(),
+            #[cfg(feature = "debug_dll")]
+            DllVariant::Debug => Ok(Cow::Borrowed(include_bytes!("../hachimi-debug.dll")))
+        }
+    }
+
+    // Avoids decompressing the DLL just to get its size
+    #[cfg(feature = "compress_dll")]
+    fn embedded_size() -> usize {
+        Self::expected_dll_size().unwrap_or_default()
+    }
+
+    #[cfg(not(feature = "compress_dll"))]
+    fn embedded_size() -> usize {
+        include_bytes!("../hachimi.dll").len()
+    }
+
+    /// Describes the DLL of the selected variant.
+    pub fn payload_info(&self) -> PayloadInfo {
+        match self.variant {
+            DllVariant::Release => PayloadInfo {
+                compressed: cfg!(feature = "compress_dll"),
+                embedded_size: Self::embedded_size()
+            },
+            #[cfg(feature = "debug_dll")]
+            DllVariant::Debug => PayloadInfo {
+                compressed: false,
+                embedded_size: include_bytes!("../hachimi-debug.dll").len()
+            }
+        }
+    }
+
+    /// Makes sure that the packaged DLL is a valid PE file with a version resource.
+    pub fn verify_embedded_dll() -> Result<(), Error> {
+        Self::verify_dll_image(&Self::get_embedded_dll()?)
+    }
+
+    fn verify_dll_image(dll: &[u8]) -> Result<(), Error> {
+        if pelite::PeFile::from_bytes(dll).is_err() || utils::read_pe_version_info(dll).is_none() {
+            return Err(Error::InvalidDll);
+        }
+        Ok(())
+    }
+
+    /// SHA-256 of the packaged DLL, computed at build time so it's available without decompressing it.
+    pub fn expected_dll_hash() -> [u8; 32] {
+        *include_bytes!(concat!(env!("OUT_DIR"), "/hachimi.dll.sha256"))
+    }
+
+    // The release hash is computed at build time, the debug DLL is small enough to hash here
+    fn variant_dll_hash(&self) -> Result<[u8; 32], Error> {
+        match self.variant {
+            DllVariant::Release => Ok(Self::expected_dll_hash()),
+            #[cfg(feature = "debug_dll")]
+            DllVariant::Debug => Ok(Sha256::digest(Self::get_variant_dll(self.variant)?).into())
+        }
+    }
+
+    pub fn embedded_version() -> Option<String> {
+        let dll = Self::get_embedded_dll().ok()?;
+        let version_info = utils::read_pe_version_info(&dll)?;
+        version_info.value(Self::LANG_NEUTRAL_UNICODE, "ProductVersion")
+            .filter(|v| !v.is_empty())
+            .or_else(|| version_info.value(Self::LANG_NEUTRAL_UNICODE, "FileVersion"))
+    }
+
+    /// Version info of the DLL that will be installed, which depends on the selected variant.
+    pub fn variant_version_info(&self) -> Option<TargetVersionInfo> {
+        Self::parse_version_info(&Self::get_variant_dll(self.variant).ok()?)
+    }
+
+    pub fn is_update_available(&self) -> bool {
+        let Some(target) = self.get_hachimi_installed_target() else {
+            return false;
+        };
+        let Some(embedded_version) = self.variant_version_info().and_then(|v| v.parse_semver()) else {
+            return false;
+        };
+
+        // Offer an update if we can't tell which version is installed
+        let installed_version = self.get_target_version_info(target);
+        match installed_version.as_ref().and_then(|v| v.parse_semver()) {
+            Some(installed_version) => installed_version < embedded_version,
+            None => true
+        }
+    }
+
+    fn verify_dll(&self, path: &Path, dll: &[u8]) -> Result<(), Error> {
+        let expected_hash: [u8; 32] = Sha256::digest(dll).into();
+        let written_hash: [u8; 32] = Sha256::digest(self.fs.read(path)?).into();
+        if written_hash != expected_hash {
+            return Err(Error::VerificationFailed);
+        }
+        Ok(())
+    }
+
+    fn with_target(&self, target: Target) -> Installer {
+        Installer {
+            target,
+            custom_target: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn migrate_target(&self, to: Target) -> Result<InstallOutcome, Error> {
+        let from = self.get_hachimi_installed_target().ok_or(Error::NotInstalled)?;
+        let new_installer = self.with_target(to);
+        if from == to {
+            return new_installer.install();
+        }
+
+        new_installer.pre_install()?;
+        let outcome = new_installer.install()?;
+
+        // Roll back the new install if we can't finish the migration
+        let res = new_installer.post_install().and_then(|_| self.with_target(from).uninstall());
+        if let Err(e) = res {
+            _ = new_installer.uninstall();
+            return Err(e);
+        }
+
+        Ok(outcome)
+    }
+
+    /// Installs or updates Hachimi only when needed. Returns `NoChange` if the current target
+    /// already has the packaged version or a newer one.
+    pub fn ensure_installed(&self) -> Result<InstallOutcome, Error> {
+        if let Some(installed) = self.get_custom_target_version_info().filter(|v| self.is_hachimi(v)) {
+            let embedded = Self::parse_version_info(&Self::get_variant_dll(self.variant)?).unwrap_or_default();
+            let up_to_date = match compare_versions(&installed, &embedded) {
+                Some(ordering) => ordering != std::cmp::Ordering::Less,
+                None => installed.get_version().is_some() && installed.get_version() == embedded.get_version()
+            };
+            if up_to_date {
+                return Ok(InstallOutcome::NoChange);
+            }
+            return self.install();
+        }
+
+        // Installed as another target, move it over instead of installing twice
+        match self.get_hachimi_installed_target() {
+            Some(target) if self.custom_target.is_none() && target != self.target => self.migrate_target(self.target),
+            _ => self.install()
+        }
+    }
+
+    pub fn repair(&self) -> Result<InstallOutcome, Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        if !self.read_version_info(&path).map(|v| self.is_hachimi(&v)).unwrap_or(false) {
+            return Err(Error::NotHachimi);
+        }
+
+        self.install()
+    }
+
+    /// Re-deploys the packaged DLL over the installed copy of Hachimi. Only the DLL itself
+    /// is written; the `hachimi` directory and the config inside of it are left untouched.
+    pub fn update(&self) -> Result<InstallOutcome, Error> {
+        if self.get_custom_target_version_info().map(|v| self.is_hachimi(&v)).unwrap_or(false) {
+            return self.install();
+        }
+
+        let target = self.get_hachimi_installed_target().ok_or(Error::NotInstalled)?;
+        self.with_target(target).install()
+    }
+
+    pub fn install_checked(&self) -> Result<InstallOutcome, Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        if let Some(installed) = self.read_version_info(&path).filter(|v| self.is_hachimi(v)) {
+            // Versions that can't be compared are treated as already installed too
+            let embedded = self.variant_version_info().unwrap_or_default();
+            if compare_versions(&installed, &embedded) != Some(std::cmp::Ordering::Less) {
+                return Err(Error::AlreadyInstalled);
+            }
+        }
+
+        self.install()
+    }
+
+    const BACKUP_SUFFIX: &str = ".hachimi-backup";
+    fn get_backup_path_internal(path: &Path) -> PathBuf {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(Self::BACKUP_SUFFIX);
+        backup_path.into()
+    }
+
+    pub fn backup_path(&self, target: Target) -> Option<PathBuf> {
+        Some(Self::get_backup_path_internal(&self.get_target_path(target)?))
+    }
+
+    pub fn has_backup(&self, target: Target) -> bool {
+        self.backup_path(target).map(|p| self.fs.is_file(&p)).unwrap_or(false)
+    }
+
+    /// Backups are stored next to their target, so every target's location is checked.
+    pub fn list_backups(&self) -> Vec<(Target, PathBuf, Option<TargetVersionInfo>)> {
+        self.target_paths()
+            .into_iter()
+            .map(|(target, path)| (target, Self::get_backup_path_internal(&path)))
+            .filter(|(_, backup_path)| self.fs.is_file(backup_path))
+            .map(|(target, backup_path)| {
+                let version_info = self.read_version_info(&backup_path);
+                (target, backup_path, version_info)
+            })
+            .collect()
+    }
+
+    fn needs_backup(&self, path: &Path) -> bool {
+        // Don't back up an older version of Hachimi
+        self.fs.is_file(path) && !self.read_version_info(path).map(|v| self.is_hachimi(&v)).unwrap_or(false)
+    }
+
+    /// Returns whether a backup was made.
+    fn backup_target(&self, path: &Path) -> Result<bool, Error> {
+        if !self.needs_backup(path) {
+            return Ok(false);
+        }
+
+        // Never overwrite an existing backup, it might be the only copy of the original DLL
+        let backup_path = Self::get_backup_path_internal(path);
+        if self.fs.exists(&backup_path) {
+            return Err(Error::BackupExists(backup_path));
+        }
+
+        self.fs.rename(path, &backup_path)?;
+        info!("Backed up {} to {}", path.display(), backup_path.display());
+        Ok(true)
+    }
+
+    fn restore_target_backup(&self, path: &Path) -> Result<(), Error> {
+        let backup_path = Self::get_backup_path_internal(path);
+        if self.fs.is_file(&backup_path) {
+            self.fs.rename(&backup_path, path)?;
+            info!("Restored backup {}", backup_path.display());
+        }
+
+        Ok(())
+    }
+
+    // dxgi.dll becomes dxgi.hachimi-chain.dll
+    const CHAIN_EXTENSION: &str = "hachimi-chain.dll";
+    fn get_chain_path_internal(path: &Path) -> PathBuf {
+        path.with_extension(Self::CHAIN_EXTENSION)
+    }
+
+    pub fn chain_path(&self, target: Target) -> Option<PathBuf> {
+        Some(Self::get_chain_path_internal(&self.get_target_path(target)?))
+    }
+
+    /// Installs to `target`, keeping an existing non-Hachimi DLL (e.g. ReShade) there by renaming it
+    /// to the forward name (see `chain_path`) instead of backing it up, so that Hachimi can forward to it.
+    /// The rename is reversed by `uninstall`.
+    pub fn install_chained(&self, target: Target) -> Result<InstallOutcome, Error> {
+        if Self::is_game_running() {
+            return Err(Error::GameRunning);
+        }
+
+        let installer = self.with_target(target);
+        let path = installer.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        installer.check_target_path(&path)?;
+
+        let chain_path = Self::get_chain_path_internal(&path);
+        let chained = installer.needs_backup(&path);
+        if chained {
+            // Same as backups, never overwrite an existing chained DLL
+            if self.fs.exists(&chain_path) {
+                return Err(Error::BackupExists(chain_path));
+            }
+            self.fs.rename(&path, &chain_path)?;
+            info!("Chained {} to {}", path.display(), chain_path.display());
+        }
+
+        let res = installer.install();
+        if res.is_err() && chained {
+            _ = self.fs.rename(&chain_path, &path);
+        }
+        res
+    }
+
+    fn restore_chained_target(&self, path: &Path) -> Result<(), Error> {
+        // A restored backup takes priority, don't overwrite it
+        let chain_path = Self::get_chain_path_internal(path);
+        if self.fs.is_file(&chain_path) && !self.fs.exists(path) {
+            self.fs.rename(&chain_path, path)?;
+            info!("Restored chained DLL {}", chain_path.display());
+        }
+
+        Ok(())
+    }
+
+    fn remove_target_backup(&self, path: &Path) -> Result<(), Error> {
+        let backup_path = Self::get_backup_path_internal(path);
+        if self.fs.is_file(&backup_path) {
+            self.fs.remove_file(&backup_path)?;
+            info!("Deleted backup {}", backup_path.display());
+        }
+
+        Ok(())
+    }
+
+    pub fn plan_install(&self) -> Result<InstallPlan, Error> {
+        let target_path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        Ok(InstallPlan {
+            overwrite: self.fs.is_file(&target_path),
+            backup: self.make_backup && self.needs_backup(&target_path),
+            embedded_version: self.variant_version_info().and_then(|v| v.get_version().map(str::to_owned)),
+            target_path
+        })
+    }
+
+    pub fn plan_uninstall(&self) -> Result<UninstallPlan, Error> {
+        let target_path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        Ok(UninstallPlan {
+            restore_backup: self.fs.is_file(&Self::get_backup_path_internal(&target_path)),
+            target_path
+        })
+    }
+
+    pub fn post_install(&self) -> Result<(), Error> {
+        match TargetType::from(self.target) {
+            TargetType::DotLocal => {
+                match Hive::LocalMachine.open(
+                    r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options",
+                    registry::Security::Read | registry::Security::SetValue
+                ) {
+                    Ok(regkey) => {
+                        if regkey.value("DevOverrideEnable").ok()
+                            .map(|v| !matches!(v, registry::Data::U32(..)))
+                            .unwrap_or(true)
+                        {
+                            let res = unsafe {
+                                MessageBoxW(
+                                    self.hwnd.as_ref(),
+                                    &HSTRING::from(i18n::tr(Msg::EnableRedirection)),
+                                    &HSTRING::from(i18n::tr(Msg::InstallTitle)),
+                                    MB_ICONINFORMATION | MB_OKCANCEL
+                                )
+                            };
+                            if res == IDOK {
+                                regkey.set_value("DevOverrideEnable", &registry::Data::U32(1))?;
+                                unsafe {
+                                    MessageBoxW(
+                                        self.hwnd.as_ref(),
+                                        &HSTRING::from(i18n::tr(Msg::RestartToApply)),
+                                        &HSTRING::from(i18n::tr(Msg::RedirectionEnabledTitle)),
+                                        MB_ICONINFORMATION | MB_OK
+                                    );
+                                }
+                            }
+                        }
+                    },
+                    Err(e) => {
+                        unsafe { MessageBoxW(
+                            self.hwnd.as_ref(),
+                            &HSTRING::from(i18n::tr_args(Msg::RegistryOpenFailed, &[&e])),
+                            &HSTRING::from(i18n::tr(Msg::WarningTitle)),
+                            MB_OK | MB_ICONWARNING
+                        )};
+                    }
+                }
+            },
+            TargetType::PluginShim => {
+                self.check_plugin_subdir()?;
+                let dest_dll = self.get_dest_plugin_path().ok_or(Error::NoInstallDir)?;
+                let src_dll = self.get_src_plugin_path().ok_or(Error::NoInstallDir)?;
+
+                if self.fs.exists(&src_dll) {
+                    self.fs.create_dir_all(dest_dll.parent().unwrap())?;
+                    self.fs.copy(&src_dll, &dest_dll)?;
+                    self.fs.remove_file(&src_dll)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn uninstall(&self) -> Result<(), Error> {
+        self.uninstall_internal(true)
+    }
+
+    fn uninstall_internal(&self, restore_backup: bool) -> Result<(), Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        self.check_target_path(&path)?;
+        if let Some(version_info) = self.read_version_info(&path) {
+            if !self.is_hachimi(&version_info) {
+                return Err(Error::NotHachimi);
+            }
+        }
+
+        self.uninstall_from(self.target, &utils::to_extended_path(&path), restore_backup).map_err(|e| e.with_path(&path))
+    }
+
+    /// Uninstalls Hachimi from every target it's installed as. If `restore_backups` is false,
+    /// the backed up DLLs are deleted instead of being put back.
+    pub fn uninstall_all(&self, restore_backups: bool) -> Result<Vec<Target>, Error> {
+        let mut cleaned = Vec::new();
+        for target in self.all_hachimi_targets() {
+            self.with_target(target).uninstall_internal(restore_backups)?;
+            cleaned.push(target);
+        }
+        Ok(cleaned)
+    }
+
+    pub fn uninstall_force(&self) -> Result<(), Error> {
+        let path = self.get_current_target_path().ok_or(Error::NoInstallDir)?;
+        self.check_target_path(&path)?;
+        self.uninstall_from(self.target, &utils::to_extended_path(&path), true).map_err(|e| e.with_path(&path))
+    }
+
+    fn clear_readonly(&self, path: &Path) -> Result<(), Error> {
+        let Ok(metadata) = self.fs.metadata(path) else {
+            return Ok(());
+        };
+
+        if metadata.readonly {
+            self.fs.set_readonly(path, false)?;
+        }
+        Ok(())
+    }
+
+    // The backup is only touched once the target is gone, so a failed uninstall never loses it
+    fn uninstall_from(&self, target: Target, path: &Path, restore_backup: bool) -> Result<(), Error> {
+        if Self::is_game_running() {
+            return Err(Error::GameRunning);
+        }
+
+        self.clear_readonly(path)?;
+        self.fs.remove_file(path)?;
+        info!("Uninstalled {}", path.display());
+        if restore_backup {
+            self.restore_target_backup(path)?;
+        }
+        else {
+            self.remove_target_backup(path)?;
+        }
+        self.restore_chained_target(path)?;
+
+        match TargetType::from(target) {
+            TargetType::DotLocal => {
+                // Only remove if its empty
+                _ = self.fs.remove_dir(path.parent().unwrap());
+            },
+            TargetType::PluginShim => {
+                let dest_dll = self.get_dest_plugin_path_internal(target).ok_or(Error::NoInstallDir)?;
+                let src_dll = self.get_src_plugin_path_internal(target).ok_or(Error::NoInstallDir)?;
+                if !self.fs.exists(&src_dll) {
+                    self.fs.copy(&dest_dll, &src_dll)?;
+                    self.fs.remove_file(&dest_dll)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_dest_plugin_path_internal(&self, target: Target) -> Option<PathBuf> {
+        Some(self.resolved_install_dir()?.join(format!("hachimi\\{}", target.dll_name())))
+    }
+
+    pub fn get_dest_plugin_path(&self) -> Option<PathBuf> {
+        self.get_dest_plugin_path_internal(self.target)
+    }
+
+    fn get_src_plugin_path_internal(&self, target: Target) -> Option<PathBuf> {
+        Some(self.plugin_dir()?.join(target.dll_name()))
+    }
+
+    pub fn get_src_plugin_path(&self) -> Option<PathBuf> {
+        self.get_src_plugin_path_internal(self.target)
+    }
+}
+
+impl std::fmt::Debug for Installer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The filesystem backend is left out, it's an implementation detail
+        f.debug_struct("Installer")
+            .field("install_dir", &self.install_dir)
+            .field("target", &self.target)
+            .field("custom_target", &self.custom_target)
+            .field("system_dir", &self.system_dir)
+            .field("variant", &self.variant)
+            .field("prevent_downgrade", &self.prevent_downgrade)
+            .field("make_backup", &self.make_backup)
+            .field("plugin_subdir", &self.plugin_subdir)
+            .field("product_name", &self.product_name)
+            .field("hwnd", &self.hwnd)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+pub struct InstallerBuilder {
+    install_dir: Option<PathBuf>,
+    target: Option<Target>,
+    custom_target: Option<String>,
+    variant: Option<DllVariant>,
+    prevent_downgrade: bool,
+    make_backup: Option<bool>,
+    plugin_subdir: Option<PathBuf>,
+    product_name: Option<String>,
+    fs: Option<Arc<dyn FileSystem>>,
+    detectors: Option<Arc<[Box<dyn InstallDirDetector>]>>
+}
+
+impl InstallerBuilder {
+    pub fn new() -> InstallerBuilder {
+        InstallerBuilder::default()
+    }
+
+    pub fn install_dir(mut self, install_dir: impl Into<PathBuf>) -> InstallerBuilder {
+        self.install_dir = Some(install_dir.into());
+        self
+    }
+
+    pub fn target(mut self, target: Target) -> InstallerBuilder {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn custom_target(mut self, custom_target: impl Into<String>) -> InstallerBuilder {
+        self.custom_target = Some(custom_target.into());
+        self
+    }
+
+    pub fn variant(mut self, variant: DllVariant) -> InstallerBuilder {
+        self.variant = Some(variant);
+        self
+    }
+
+    pub fn prevent_downgrade(mut self, prevent_downgrade: bool) -> InstallerBuilder {
+        self.prevent_downgrade = prevent_downgrade;
+        self
+    }
+
+    pub fn make_backup(mut self, make_backup: bool) -> InstallerBuilder {
+        self.make_backup = Some(make_backup);
+        self
+    }
+
+    pub fn plugin_subdir(mut self, plugin_subdir: impl Into<PathBuf>) -> InstallerBuilder {
+        self.plugin_subdir = Some(plugin_subdir.into());
+        self
+    }
+
+    pub fn product_name(mut self, product_name: impl Into<String>) -> InstallerBuilder {
+        self.product_name = Some(product_name.into());
+        self
+    }
+
+    pub fn filesystem(mut self, fs: Arc<dyn FileSystem>) -> InstallerBuilder {
+        self.fs = Some(fs);
+        self
+    }
+
+    /// Replaces the detectors used to find the install dir, tried in order. Start from
+    /// `detect::default_detectors()` to keep the built-in ones.
+    pub fn detectors(mut self, detectors: Vec<Box<dyn InstallDirDetector>>) -> InstallerBuilder {
+        self.detectors = Some(detectors.into());
+        self
+    }
+
+    /// Unset fields fall back to the same defaults as `Installer::custom`, so the
+    /// install dir is autodetected if it wasn't given.
+    pub fn build(self) -> Installer {
+        let detectors = self.detectors.unwrap_or_else(|| detect::default_detectors().into());
+        let mut installer = Installer::custom_with_detectors(
+            self.install_dir, self.target.unwrap_or_default(), self.custom_target, detectors
+        );
+        installer.set_variant(self.variant.unwrap_or_default());
+        installer.prevent_downgrade = self.prevent_downgrade;
+        installer.make_backup = self.make_backup.unwrap_or(true);
+        installer.plugin_subdir = self.plugin_subdir;
+        if let Some(product_name) = self.product_name {
+            installer.product_name = product_name;
+        }
+        match self.fs {
+            Some(fs) => installer.with_filesystem(fs),
+            None => installer
+        }
+    }
+}
+
+impl Default for Installer {
+    fn default() -> Installer {
+        // Prefer the saved config over autodetection
+        Self::load_config().unwrap_or_else(|| Installer {
+            install_dir: Self::detect_install_dir(),
+            target: Target::default(),
+            custom_target: None,
+            system_dir: get_system_directory(),
+            fs: Arc::new(RealFileSystem),
+            version_info_cache: RefCell::default(),
+            variant: DllVariant::default(),
+            prevent_downgrade: false,
+            make_backup: true,
+            plugin_subdir: None,
+            product_name: HACHIMI_PRODUCT_NAME.to_owned(),
+            detectors: detect::default_detectors().into(),
+            hwnd: None
+        })
+    }
+}
+
+// Keep the declaration order in sync with VALUES, the GUI uses the discriminant as the combo box index
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Target {
+    UnityPlayer,
+    Dinput8,
+    CriManaVpx,
+    D3d11,
+    Dxgi,
+    D3d9
+}
+
+impl Target {
+    // Ordered from most to least reliable
+    pub const VALUES: &[Self] = &[
+        Self::UnityPlayer,
+        Self::Dinput8,
+        Self::CriManaVpx,
+        Self::D3d11,
+        Self::Dxgi,
+        Self::D3d9
     ];
 
     pub fn dll_name(&self) -> &'static str {
         match self {
             Self::UnityPlayer => "UnityPlayer.dll",
-            Self::CriManaVpx => "cri_mana_vpx.dll"
+            Self::Dinput8 => "dinput8.dll",
+            Self::CriManaVpx => "cri_mana_vpx.dll",
+            Self::D3d11 => "d3d11.dll",
+            Self::Dxgi => "dxgi.dll",
+            Self::D3d9 => "d3d9.dll"
         }
     }
 }
@@ -294,6 +1780,87 @@ impl Default for Target {
     }
 }
 
+impl std::str::FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Accept the name with or without the extension
+        Target::VALUES.iter()
+            .find(|t| {
+                let dll_name = t.dll_name();
+                dll_name.eq_ignore_ascii_case(s) || dll_name[..dll_name.len() - 4].eq_ignore_ascii_case(s)
+            })
+            .copied()
+            .ok_or_else(|| Error::InvalidTarget(s.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.dll_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Target {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Target {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(|_| serde::de::Error::custom(format!("invalid target: {}", name)))
+    }
+}
+
+const FLATPAK_STEAM_ID: &str = "com.valvesoftware.Steam";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SteamFlavor {
+    Native,
+    Flatpak
+}
+
+impl std::fmt::Display for SteamFlavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SteamFlavor::Native => write!(f, "native"),
+            SteamFlavor::Flatpak => write!(f, "flatpak")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Arch {
+    X86,
+    X64,
+    Arm64
+}
+
+impl Arch {
+    fn from_machine(machine: u16) -> Option<Self> {
+        match machine {
+            0x014c => Some(Self::X86),
+            0x8664 => Some(Self::X64),
+            0xaa64 => Some(Self::Arm64),
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arch::X86 => write!(f, "x86"),
+            Arch::X64 => write!(f, "x64"),
+            Arch::Arm64 => write!(f, "ARM64")
+        }
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum TargetType {
     DotLocal,
@@ -305,47 +1872,446 @@ impl From<Target> for TargetType {
         match value {
             Target::UnityPlayer => Self::DotLocal,
             Target::CriManaVpx => Self::PluginShim,
+            Target::Dinput8 | Target::D3d11 | Target::Dxgi | Target::D3d9 => Self::DotLocal
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InstallHealth {
+    pub problems: Vec<HealthProblem>
+}
+
+impl InstallHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum HealthProblem {
+    NoInstallDir,
+    Missing,
+    InvalidDll,
+    NotHachimi,
+    /// Neither the packaged build nor a newer version
+    HashMismatch,
+    Duplicated(Vec<Target>)
+}
+
+impl std::fmt::Display for HealthProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match Lang::current() {
+            Lang::English => self.fmt_english(f),
+            Lang::Japanese => self.fmt_japanese(f)
+        }
+    }
+}
+
+impl HealthProblem {
+    fn fmt_english(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthProblem::NoInstallDir => write!(f, "{}", Error::NoInstallDir),
+            HealthProblem::Missing => write!(f, "The target DLL does not exist"),
+            HealthProblem::InvalidDll => write!(f, "The target DLL is not a valid PE file"),
+            HealthProblem::NotHachimi => write!(f, "{}", Error::NotHachimi),
+            HealthProblem::HashMismatch => write!(f, "The installed DLL does not match the packaged version"),
+            HealthProblem::Duplicated(targets) => write!(f, "Hachimi is installed as multiple targets: {}", Self::join_targets(targets))
+        }
+    }
+
+    fn fmt_japanese(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthProblem::NoInstallDir => write!(f, "{}", Error::NoInstallDir),
+            HealthProblem::Missing => write!(f, "ターゲットDLLが存在しません"),
+            HealthProblem::InvalidDll => write!(f, "ターゲットDLLは有効なPEファイルではありません"),
+            HealthProblem::NotHachimi => write!(f, "{}", Error::NotHachimi),
+            HealthProblem::HashMismatch => write!(f, "インストールされているDLLが同梱のバージョンと一致しません"),
+            HealthProblem::Duplicated(targets) => write!(f, "Hachimiが複数のターゲットにインストールされています: {}", Self::join_targets(targets))
+        }
+    }
+
+    fn join_targets(targets: &[Target]) -> String {
+        targets.iter().map(|t| t.dll_name()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+pub struct InstallStatus {
+    pub install_dir_found: bool,
+    pub hachimi_target: Option<Target>,
+    pub installed_version: Option<String>,
+    pub current_target_occupied: bool
+}
+
+impl InstallStatus {
+    pub fn to_json(&self) -> JsonValue {
+        let mut status = HashMap::new();
+        status.insert("install_dir_found".to_owned(), JsonValue::Boolean(self.install_dir_found));
+        status.insert(
+            "hachimi_target".to_owned(),
+            self.hachimi_target.map(|t| JsonValue::String(t.dll_name().to_owned())).unwrap_or(JsonValue::Null)
+        );
+        status.insert(
+            "installed_version".to_owned(),
+            self.installed_version.clone().map(JsonValue::String).unwrap_or(JsonValue::Null)
+        );
+        status.insert("current_target_occupied".to_owned(), JsonValue::Boolean(self.current_target_occupied));
+        JsonValue::Object(status)
+    }
+}
+
+/// Which of the packaged Hachimi builds gets installed. The debug build is only
+/// available with the `debug_dll` feature.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum DllVariant {
+    #[default]
+    Release,
+    #[cfg(feature = "debug_dll")]
+    Debug
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DirInfo {
+    pub total_space: u64,
+    pub available_space: u64,
+    pub is_removable: bool
+}
+
+/// The persisted part of the installer's state.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstallerConfig {
+    pub install_dir: Option<PathBuf>,
+    pub target: String,
+    pub custom_target: Option<String>
+}
+
+impl InstallerConfig {
+    pub fn to_json(&self) -> JsonValue {
+        let mut config = HashMap::new();
+        config.insert(
+            "install_dir".to_owned(),
+            self.install_dir.as_ref()
+                .and_then(|p| p.to_str())
+                .map(|s| JsonValue::String(s.to_owned()))
+                .unwrap_or(JsonValue::Null)
+        );
+        config.insert("target".to_owned(), JsonValue::String(self.target.clone()));
+        config.insert(
+            "custom_target".to_owned(),
+            self.custom_target.clone().map(JsonValue::String).unwrap_or(JsonValue::Null)
+        );
+        JsonValue::Object(config)
+    }
+
+    pub fn from_json(value: &JsonValue) -> Option<InstallerConfig> {
+        let JsonValue::Object(config) = value else {
+            return None;
+        };
+
+        Some(InstallerConfig {
+            install_dir: match config.get("install_dir") {
+                Some(JsonValue::String(path_str)) => Some(PathBuf::from(path_str)),
+                _ => None
+            },
+            target: match config.get("target") {
+                Some(JsonValue::String(target)) => target.clone(),
+                _ => Target::default().to_string()
+            },
+            custom_target: match config.get("custom_target") {
+                Some(JsonValue::String(custom_target)) => Some(custom_target.clone()),
+                _ => None
+            }
+        })
+    }
+}
+
+impl From<&Installer> for InstallerConfig {
+    fn from(installer: &Installer) -> Self {
+        InstallerConfig {
+            install_dir: installer.install_dir.clone(),
+            target: installer.target.to_string(),
+            custom_target: installer.custom_target.clone()
         }
     }
 }
 
-#[derive(Debug, Default)]
+// Unknown targets fall back to the default, the install dir is autodetected if unset
+impl From<InstallerConfig> for Installer {
+    fn from(config: InstallerConfig) -> Self {
+        Installer::custom(config.install_dir, config.target.parse().unwrap_or_default(), config.custom_target)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadInfo {
+    pub compressed: bool,
+    /// Size of the DLL as it will be written, after decompression
+    pub embedded_size: usize
+}
+
+#[derive(Debug)]
+pub struct InstallPlan {
+    pub target_path: PathBuf,
+    pub overwrite: bool,
+    pub backup: bool,
+    pub embedded_version: Option<String>
+}
+
+#[derive(Debug)]
+pub struct UninstallPlan {
+    pub target_path: PathBuf,
+    pub restore_backup: bool
+}
+
+#[derive(Debug)]
+pub enum InstallOutcome {
+    Fresh,
+    Overwritten { previous: Option<TargetVersionInfo> },
+    /// Nothing was written, the installed version was already up to date.
+    NoChange,
+    /// GitHub couldn't be reached, so the packaged DLL was installed instead.
+    #[cfg(feature = "download")]
+    EmbeddedFallback { previous: Option<TargetVersionInfo>, error: Error }
+}
+
+impl InstallOutcome {
+    pub fn to_json(&self) -> JsonValue {
+        let previous_json = |previous: &Option<TargetVersionInfo>| {
+            previous.as_ref().map(|v| v.to_json()).unwrap_or(JsonValue::Null)
+        };
+
+        let mut outcome = HashMap::new();
+        let name = match self {
+            InstallOutcome::Fresh => "fresh",
+            InstallOutcome::Overwritten { previous } => {
+                outcome.insert("previous".to_owned(), previous_json(previous));
+                "overwritten"
+            },
+            InstallOutcome::NoChange => "no_change",
+            #[cfg(feature = "download")]
+            InstallOutcome::EmbeddedFallback { previous, error } => {
+                outcome.insert("previous".to_owned(), previous_json(previous));
+                outcome.insert("error".to_owned(), JsonValue::String(error.to_string()));
+                "embedded_fallback"
+            }
+        };
+        outcome.insert("outcome".to_owned(), JsonValue::String(name.to_owned()));
+        JsonValue::Object(outcome)
+    }
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
 pub struct TargetVersionInfo {
     pub name: Option<String>,
-    pub version: Option<String>
+    pub version: Option<String>,
+    pub file_version: Option<String>,
+    pub company_name: Option<String>,
+    pub description: Option<String>
 }
 
 impl TargetVersionInfo {
+    pub fn to_json(&self) -> JsonValue {
+        let fields = [
+            ("name", &self.name),
+            ("version", &self.version),
+            ("file_version", &self.file_version),
+            ("company_name", &self.company_name),
+            ("description", &self.description)
+        ];
+        JsonValue::Object(
+            fields.into_iter()
+                .map(|(key, value)| (key.to_owned(), value.clone().map(JsonValue::String).unwrap_or(JsonValue::Null)))
+                .collect()
+        )
+    }
+
     pub fn get_display_label(&self, target: Target) -> String {
-        let name = self.name.clone().unwrap_or_else(|| "Unknown".to_string());
+        let name = self.name.clone().unwrap_or_else(|| i18n::tr(Msg::Unknown).to_owned());
         format!("* {} ({})", target.dll_name(), name)
     }
 
+    // Some builds only set FileVersion
+    pub fn get_version(&self) -> Option<&str> {
+        self.version.as_deref()
+            .filter(|v| !v.is_empty())
+            .or(self.file_version.as_deref())
+    }
+
+    pub fn parse_semver(&self) -> Option<semver::Version> {
+        utils::parse_version(self.get_version()?)
+    }
+
+    /// Checks against `HACHIMI_PRODUCT_NAME`. Use `Installer::is_hachimi` to respect the
+    /// installer's `product_name` override.
     pub fn is_hachimi(&self) -> bool {
-        if let Some(name) = &self.name {
-            return name == "Hachimi";
-        }
-        false
+        self.is_product(HACHIMI_PRODUCT_NAME)
     }
+
+    pub fn is_product(&self, product_name: &str) -> bool {
+        [&self.name, &self.company_name, &self.description]
+            .into_iter()
+            .any(|value| value.as_deref() == Some(product_name))
+    }
+}
+
+fn escapes_base_dir(path: &Path) -> bool {
+    path.is_relative() &&
+        path.components().any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+pub fn compare_versions(a: &TargetVersionInfo, b: &TargetVersionInfo) -> Option<std::cmp::Ordering> {
+    Some(a.parse_semver()?.cmp(&b.parse_semver()?))
 }
 
 #[derive(Debug)]
 pub enum Error {
     NoInstallDir,
+    NoConfigDir,
     CannotFindTarget,
+    InvalidTarget(String),
+    CustomTargetIsGameDll(String),
+    TargetOutsideInstallDir(String),
+    TargetIsDirectory(PathBuf),
+    BackupExists(PathBuf),
+    AlreadyInstalled,
+    WouldDowngrade { installed: String, embedded: String },
+    NotHachimi,
+    NotInstalled,
+    GameRunning,
+    CannotLaunchGame,
+    ArchMismatch { game: Arch, dll: Arch },
+    InsufficientSpace { required: u64, available: u64 },
+    PermissionDenied { path: PathBuf },
+    VerificationFailed,
+    InvalidDll,
+    Decompression,
+    #[cfg(feature = "download")]
+    ReleaseAssetNotFound,
+    #[cfg(feature = "download")]
+    DownloadError(reqwest::Error),
+    #[cfg(feature = "watch")]
+    WatchError(notify::Error),
     IoError(std::io::Error),
     RegistryValueError(registry::value::Error)
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match Lang::current() {
+            Lang::English => self.fmt_english(f),
+            Lang::Japanese => self.fmt_japanese(f)
+        }
+    }
+}
+
+impl Error {
+    fn fmt_english(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::NoInstallDir => write!(f, "No install location specified"),
+            Error::NoConfigDir => write!(f, "Cannot find the config directory"),
             Error::CannotFindTarget => write!(f, "Cannot find target DLL in specified install location"),
+            Error::InvalidTarget(name) => write!(f, "Invalid target: {}", name),
+            Error::CustomTargetIsGameDll(name) => write!(f, "{} is one of the game's own DLLs and cannot be replaced", name),
+            Error::TargetOutsideInstallDir(name) => write!(f, "Target {} is outside of the install location", name),
+            Error::TargetIsDirectory(path) => write!(f, "{} is a directory, not a DLL", path.display()),
+            Error::BackupExists(path) => write!(f, "A backup of the target DLL already exists: {}", path.display()),
+            Error::AlreadyInstalled => write!(f, "This or a newer version of Hachimi is already installed"),
+            Error::WouldDowngrade { installed, embedded } => write!(
+                f, "The installed version of Hachimi ({}) is newer than the packaged version ({})", installed, embedded
+            ),
+            Error::NotHachimi => write!(f, "The target DLL is not Hachimi"),
+            Error::NotInstalled => write!(f, "Hachimi is not installed"),
+            Error::GameRunning => write!(f, "The game is currently running. Please close the game and try again."),
+            Error::CannotLaunchGame => write!(f, "Failed to launch the game"),
+            Error::ArchMismatch { game, dll } => write!(
+                f, "The game is a {} executable, but the Hachimi DLL is built for {}", game, dll
+            ),
+            Error::InsufficientSpace { required, available } => write!(
+                f, "Not enough disk space: {} bytes required, {} bytes available", required, available
+            ),
+            Error::PermissionDenied { path } => write!(
+                f, "Access denied to {}. Try running the installer as administrator.", path.display()
+            ),
+            Error::VerificationFailed => write!(f, "The written DLL does not match the packaged DLL"),
+            Error::InvalidDll => write!(f, "The DLL is not a valid PE file"),
+            Error::Decompression => write!(f, "Failed to decompress the packaged DLL"),
+            #[cfg(feature = "download")]
+            Error::ReleaseAssetNotFound => write!(f, "Cannot find hachimi.dll in the release"),
+            #[cfg(feature = "download")]
+            Error::DownloadError(e) => write!(f, "Download error: {}", e),
+            #[cfg(feature = "watch")]
+            Error::WatchError(e) => write!(f, "Watch error: {}", e),
             Error::IoError(e) => write!(f, "I/O error: {}", e),
             Error::RegistryValueError(e) => write!(f, "Registry value error: {}", e)
         }
     }
+
+    fn fmt_japanese(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NoInstallDir => write!(f, "インストール先が指定されていません"),
+            Error::NoConfigDir => write!(f, "設定フォルダが見つかりません"),
+            Error::CannotFindTarget => write!(f, "指定されたインストール先にターゲットDLLが見つかりません"),
+            Error::InvalidTarget(name) => write!(f, "無効なターゲット: {}", name),
+            Error::CustomTargetIsGameDll(name) => write!(f, "{} はゲーム本体のDLLのため置き換えできません", name),
+            Error::TargetOutsideInstallDir(name) => write!(f, "ターゲット {} はインストール先の外にあります", name),
+            Error::TargetIsDirectory(path) => write!(f, "{} はDLLではなくフォルダです", path.display()),
+            Error::BackupExists(path) => write!(f, "ターゲットDLLのバックアップが既に存在します: {}", path.display()),
+            Error::AlreadyInstalled => write!(f, "このバージョン以降のHachimiは既にインストールされています"),
+            Error::WouldDowngrade { installed, embedded } => write!(
+                f, "インストールされているHachimi ({}) は同梱のバージョン ({}) より新しいです", installed, embedded
+            ),
+            Error::NotHachimi => write!(f, "ターゲットDLLはHachimiではありません"),
+            Error::NotInstalled => write!(f, "Hachimiはインストールされていません"),
+            Error::GameRunning => write!(f, "ゲームが起動中です。ゲームを終了してから再度お試しください。"),
+            Error::CannotLaunchGame => write!(f, "ゲームを起動できませんでした"),
+            Error::ArchMismatch { game, dll } => write!(
+                f, "ゲームは{}の実行ファイルですが、HachimiのDLLは{}向けにビルドされています", game, dll
+            ),
+            Error::InsufficientSpace { required, available } => write!(
+                f, "ディスクの空き容量が不足しています: {} バイト必要、{} バイト利用可能", required, available
+            ),
+            Error::PermissionDenied { path } => write!(
+                f, "{} へのアクセスが拒否されました。管理者としてインストーラーを実行してください。", path.display()
+            ),
+            Error::VerificationFailed => write!(f, "書き込まれたDLLが同梱のDLLと一致しません"),
+            Error::InvalidDll => write!(f, "DLLは有効なPEファイルではありません"),
+            Error::Decompression => write!(f, "同梱のDLLの展開に失敗しました"),
+            #[cfg(feature = "download")]
+            Error::ReleaseAssetNotFound => write!(f, "リリースに hachimi.dll が見つかりません"),
+            #[cfg(feature = "download")]
+            Error::DownloadError(e) => write!(f, "ダウンロードエラー: {}", e),
+            #[cfg(feature = "watch")]
+            Error::WatchError(e) => write!(f, "監視エラー: {}", e),
+            Error::IoError(e) => write!(f, "I/Oエラー: {}", e),
+            Error::RegistryValueError(e) => write!(f, "レジストリ値エラー: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::RegistryValueError(e) => Some(e),
+            #[cfg(feature = "download")]
+            Error::DownloadError(e) => Some(e),
+            #[cfg(feature = "watch")]
+            Error::WatchError(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl Error {
+    fn with_path(self, path: &Path) -> Error {
+        match self {
+            Error::IoError(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Error::PermissionDenied { path: path.to_owned() }
+            },
+            e => e
+        }
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -354,8 +2320,286 @@ impl From<std::io::Error> for Error {
     }
 }
 
+#[cfg(feature = "download")]
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::DownloadError(e)
+    }
+}
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::WatchError(e)
+    }
+}
+
 impl From<registry::value::Error> for Error {
     fn from(e: registry::value::Error) -> Self {
         Error::RegistryValueError(e)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::{Path, PathBuf}, sync::Arc};
+
+    use super::*;
+    use crate::filesystem::{mock::MemoryFileSystem, FileSystem};
+
+    const INSTALL_DIR: &str = r"C:\hachimi-test\game";
+
+    fn memory_installer(install_dir: &str) -> (Installer, Arc<MemoryFileSystem>) {
+        let fs = Arc::new(MemoryFileSystem::default());
+        fs.create_dir_all(Path::new(install_dir)).unwrap();
+        let installer = Installer::custom(Some(install_dir.into()), Target::UnityPlayer, None)
+            .with_filesystem(fs.clone());
+        (installer, fs)
+    }
+
+    fn target_path(installer: &Installer) -> PathBuf {
+        installer.get_current_target_path().unwrap()
+    }
+
+    fn embedded_dll() -> Vec<u8> {
+        Installer::get_embedded_dll().unwrap().into_owned()
+    }
+
+    // Built from the .rc files in tests/fixtures by build.sh. All of them are version 1.2.3 and
+    // "Hachimi" appears in at most one field each.
+    const FIXTURE_DLL: &[u8] = include_bytes!("../tests/fixtures/hachimi-fixture.dll");
+    const COMPANY_FIXTURE_DLL: &[u8] = include_bytes!("../tests/fixtures/hachimi-company-fixture.dll");
+    const DESCRIPTION_FIXTURE_DLL: &[u8] = include_bytes!("../tests/fixtures/hachimi-description-fixture.dll");
+    const OTHER_FIXTURE_DLL: &[u8] = include_bytes!("../tests/fixtures/other-fixture.dll");
+
+    fn fixture_version_info() -> TargetVersionInfo {
+        Installer::parse_version_info(FIXTURE_DLL).unwrap()
+    }
+
+    #[test]
+    fn parse_version_info_reads_fixture() {
+        let version_info = fixture_version_info();
+        assert_eq!(version_info.name.as_deref(), Some("Hachimi"));
+        assert_eq!(version_info.version.as_deref(), Some("1.2.3"));
+        assert_eq!(version_info.file_version.as_deref(), Some("1.2.3"));
+        assert_eq!(version_info.company_name.as_deref(), Some("Fixture Company"));
+        assert_eq!(version_info.description.as_deref(), Some("Fixture DLL"));
+        assert_eq!(version_info.get_version(), Some("1.2.3"));
+    }
+
+    #[test]
+    fn parse_version_info_rejects_non_pe() {
+        assert!(Installer::parse_version_info(b"not a dll").is_none());
+    }
+
+    #[test]
+    fn version_info_is_hachimi_by_name() {
+        let version_info = fixture_version_info();
+        assert!(version_info.is_hachimi());
+        assert!(!version_info.is_product("Other"));
+    }
+
+    #[test]
+    fn version_info_is_hachimi_by_company_name() {
+        let version_info = Installer::parse_version_info(COMPANY_FIXTURE_DLL).unwrap();
+        assert_eq!(version_info.name.as_deref(), Some("Fixture"));
+        assert_eq!(version_info.company_name.as_deref(), Some("Hachimi"));
+        assert!(version_info.is_hachimi());
+    }
+
+    #[test]
+    fn version_info_is_hachimi_by_description() {
+        let version_info = Installer::parse_version_info(DESCRIPTION_FIXTURE_DLL).unwrap();
+        assert_eq!(version_info.name.as_deref(), Some("Fixture"));
+        assert_eq!(version_info.description.as_deref(), Some("Hachimi"));
+        assert!(version_info.is_hachimi());
+    }
+
+    #[test]
+    fn version_info_is_not_hachimi() {
+        let version_info = Installer::parse_version_info(OTHER_FIXTURE_DLL).unwrap();
+        assert!(!version_info.is_hachimi());
+        assert!(version_info.is_product("Fixture"));
+        assert!(!TargetVersionInfo::default().is_hachimi());
+    }
+
+    #[test]
+    fn installer_is_hachimi_respects_product_name() {
+        let mut installer = Installer::custom(None, Target::UnityPlayer, None);
+        installer.product_name = "Fixture".to_owned();
+        assert!(installer.is_hachimi(&Installer::parse_version_info(OTHER_FIXTURE_DLL).unwrap()));
+        assert!(!installer.is_hachimi(&fixture_version_info()));
+    }
+
+    #[test]
+    fn version_info_display_label() {
+        let version_info = fixture_version_info();
+        assert_eq!(version_info.get_display_label(Target::UnityPlayer), "* UnityPlayer.dll (Hachimi)");
+        assert_eq!(
+            TargetVersionInfo::default().get_display_label(Target::UnityPlayer),
+            format!("* UnityPlayer.dll ({})", i18n::tr(Msg::Unknown))
+        );
+    }
+
+    #[test]
+    fn target_version_info_reads_installed_file() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        assert_eq!(installer.get_target_version_info(Target::UnityPlayer), None);
+
+        fs.add_file(target_path(&installer), FIXTURE_DLL);
+        assert_eq!(installer.get_target_version_info(Target::UnityPlayer), Some(fixture_version_info()));
+    }
+
+    struct FixedDetector(&'static str);
+
+    impl InstallDirDetector for FixedDetector {
+        fn detect(&self) -> Option<PathBuf> {
+            Some(PathBuf::from(self.0))
+        }
+    }
+
+    #[test]
+    fn builder_uses_custom_detectors() {
+        let installer = Installer::builder()
+            .detectors(vec![Box::new(FixedDetector(INSTALL_DIR))])
+            .filesystem(Arc::new(MemoryFileSystem::default()))
+            .build();
+        assert_eq!(installer.install_dir.as_deref(), Some(Path::new(INSTALL_DIR)));
+        assert_eq!(installer.detected_install_dirs(), vec![PathBuf::from(INSTALL_DIR)]);
+    }
+
+    #[test]
+    fn embedded_dll_is_valid() {
+        Installer::verify_embedded_dll().unwrap()
+    }
+
+    #[test]
+    fn install_writes_packaged_dll() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        assert_eq!(path, Path::new(INSTALL_DIR).join(r"umamusume.exe.local\UnityPlayer.dll"));
+
+        let outcome = installer.install().unwrap();
+        assert!(matches!(outcome, InstallOutcome::Fresh));
+        assert_eq!(fs.file(&path), Some(embedded_dll()));
+        assert!(!fs.exists(&installer.backup_path(Target::UnityPlayer).unwrap()));
+    }
+
+    #[test]
+    fn target_path_resolves_symlinked_install_dir() {
+        let link = r"C:\hachimi-test\link";
+        let (_, fs) = memory_installer(INSTALL_DIR);
+        fs.add_link(link, INSTALL_DIR);
+        let installer = Installer::custom(Some(link.into()), Target::UnityPlayer, None)
+            .with_filesystem(fs.clone());
+
+        assert_eq!(installer.resolved_install_dir(), Some(PathBuf::from(INSTALL_DIR)));
+        assert_eq!(target_path(&installer), Path::new(INSTALL_DIR).join(r"umamusume.exe.local\UnityPlayer.dll"));
+    }
+
+    #[test]
+    fn install_to_unc_install_dir() {
+        let (installer, fs) = memory_installer(r"\\localhost\hachimi-test\game");
+        let path = target_path(&installer);
+        assert_eq!(path, Path::new(r"\\localhost\hachimi-test\game\umamusume.exe.local\UnityPlayer.dll"));
+
+        installer.install().unwrap();
+        assert_eq!(fs.file(&path), Some(embedded_dll()));
+    }
+
+    #[test]
+    fn install_backs_up_foreign_dll() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        fs.add_file(&path, b"original");
+
+        let outcome = installer.install().unwrap();
+        assert!(matches!(outcome, InstallOutcome::Overwritten { .. }));
+        assert_eq!(fs.file(&path), Some(embedded_dll()));
+        assert_eq!(fs.file(installer.backup_path(Target::UnityPlayer).unwrap()), Some(b"original".to_vec()));
+    }
+
+    #[test]
+    fn update_keeps_hachimi_config() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        installer.install().unwrap();
+        let config_path = Path::new(INSTALL_DIR).join(r"hachimi\config.json");
+        let config = br#"{"debug_mode": true}"#;
+        fs.add_file(&config_path, config);
+
+        installer.update().unwrap();
+        assert_eq!(fs.file(&config_path), Some(config.to_vec()));
+        assert_eq!(fs.file(target_path(&installer)), Some(embedded_dll()));
+    }
+
+    #[test]
+    fn install_checked_refuses_same_version() {
+        let (installer, _) = memory_installer(INSTALL_DIR);
+        installer.install().unwrap();
+
+        assert!(matches!(installer.install_checked(), Err(Error::AlreadyInstalled)));
+    }
+
+    #[test]
+    fn verify_installation_after_install() {
+        let (installer, _) = memory_installer(INSTALL_DIR);
+        installer.install().unwrap();
+
+        assert!(installer.verify_installation().is_healthy());
+    }
+
+    #[cfg(feature = "debug_dll")]
+    #[test]
+    fn verify_installation_accepts_debug_variant() {
+        let (mut installer, _) = memory_installer(INSTALL_DIR);
+        installer.set_variant(DllVariant::Debug);
+        installer.install().unwrap();
+
+        assert!(installer.verify_installation().is_healthy());
+        assert_eq!(installer.payload_info().embedded_size, include_bytes!("../hachimi-debug.dll").len());
+    }
+
+    #[test]
+    fn uninstall_removes_hachimi() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        installer.install().unwrap();
+
+        installer.uninstall().unwrap();
+        assert!(!fs.exists(&path));
+        // The empty DotLocal dir is cleaned up too
+        assert!(!fs.exists(path.parent().unwrap()));
+    }
+
+    #[test]
+    fn uninstall_restores_backup() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        fs.add_file(&path, b"original");
+        installer.install().unwrap();
+
+        installer.uninstall().unwrap();
+        assert_eq!(fs.file(&path), Some(b"original".to_vec()));
+        assert!(!fs.exists(&installer.backup_path(Target::UnityPlayer).unwrap()));
+    }
+
+    #[test]
+    fn uninstall_refuses_foreign_dll() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        fs.add_file(&path, b"original");
+
+        assert!(matches!(installer.uninstall(), Err(Error::NotHachimi)));
+        assert_eq!(fs.file(&path), Some(b"original".to_vec()));
+    }
+
+    #[test]
+    fn uninstall_force_removes_foreign_dll() {
+        let (installer, fs) = memory_installer(INSTALL_DIR);
+        let path = target_path(&installer);
+        fs.add_file(&path, b"original");
+
+        installer.uninstall_force().unwrap();
+        assert!(!fs.exists(&path));
+    }
+}
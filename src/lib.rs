@@ -0,0 +1,10 @@
+pub mod installer;
+pub mod detect;
+pub mod filesystem;
+pub mod i18n;
+pub mod utils;
+pub mod cli;
+pub mod gui;
+mod resource;
+#[cfg(feature = "download")]
+mod download;
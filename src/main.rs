@@ -1,14 +1,6 @@
 #![windows_subsystem = "windows"]
 
-mod installer;
-mod resource;
-mod utils;
-mod cli;
-mod gui;
-
-#[cfg(feature = "compress_dll")]
-#[macro_use]
-extern crate include_bytes_zstd;
+use hachimi_installer::{cli, gui, installer};
 
 fn main() -> Result<(), installer::Error> {
     // Command line interface / Unattended mode
@@ -1,22 +1,35 @@
-use std::{ffi::{CStr, OsString}, os::windows::ffi::OsStringExt, path::{Path, PathBuf}};
+use std::{
+    ffi::{c_void, CStr, OsString},
+    os::windows::ffi::OsStringExt,
+    path::{Component, Path, PathBuf, Prefix},
+};
 
 use pelite::resources::version_info::VersionInfo;
 use windows::{
-    core::HSTRING,
+    core::{s, w, HSTRING, PCWSTR, PWSTR},
     Win32::{
-        Foundation::{HWND, MAX_PATH, RECT},
+        Foundation::{CloseHandle, HANDLE, HWND, MAX_PATH, RECT},
         System::{
             Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
+            Console::{AttachConsole, ATTACH_PARENT_PROCESS},
+            LibraryLoader::{GetModuleHandleW, GetProcAddress},
             Diagnostics::ToolHelp::{
                 CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32,
                 TH32CS_SNAPALL,
             },
-            SystemInformation::GetSystemDirectoryW,
+            SystemInformation::{GetSystemDirectoryW, IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_UNKNOWN},
+            Threading::{
+                GetProcessInformation, IsWow64Process2, OpenProcess, ProcessMachineTypeInfo,
+                QueryFullProcessImageNameW, PROCESS_MACHINE_INFORMATION, PROCESS_NAME_WIN32,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            },
         },
+        Storage::FileSystem::{GetDiskFreeSpaceExW, GetDriveTypeW, GetVolumePathNameW},
         UI::{
             Shell::{
                 FileOpenDialog, IFileOpenDialog, IShellItem, SHCreateItemFromParsingName,
-                FOS_FILEMUSTEXIST, FOS_PICKFOLDERS, SIGDN_FILESYSPATH,
+                SHGetKnownFolderPath, FOLDERID_RoamingAppData, FOS_FILEMUSTEXIST,
+                FOS_PICKFOLDERS, KF_FLAG_DEFAULT, SIGDN_FILESYSPATH,
             },
             WindowsAndMessaging::{GetDesktopWindow, GetWindowRect, SetWindowPos, SWP_NOSIZE},
         },
@@ -59,6 +72,31 @@ pub fn read_pe_version_info<'a>(image: &'a [u8]) -> Option<VersionInfo<'a>> {
         .ok()
 }
 
+pub fn read_pe_machine(image: &[u8]) -> Option<u16> {
+    // Read it manually since pelite only parses one bitness at a time
+    let read_u16 = |offset: usize| Some(u16::from_le_bytes(image.get(offset..offset + 2)?.try_into().ok()?));
+    let read_u32 = |offset: usize| Some(u32::from_le_bytes(image.get(offset..offset + 4)?.try_into().ok()?));
+
+    if read_u16(0)? != 0x5a4d {
+        return None;
+    }
+    let nt_headers_offset = read_u32(0x3c)? as usize;
+    if read_u32(nt_headers_offset)? != 0x4550 {
+        return None;
+    }
+    read_u16(nt_headers_offset + 4)
+}
+
+pub fn read_pe_import_names(image: &[u8]) -> Option<Vec<String>> {
+    let imports = pelite::PeFile::from_bytes(image).ok()?.imports().ok()?;
+    Some(
+        imports
+            .iter()
+            .filter_map(|desc| desc.dll_name().ok()?.to_str().ok().map(|s| s.to_owned()))
+            .collect(),
+    )
+}
+
 pub fn open_select_folder_dialog<P: AsRef<Path>>(
     owner: HWND,
     default_folder: Option<P>,
@@ -88,24 +126,87 @@ pub fn open_select_folder_dialog<P: AsRef<Path>>(
     Some(path_str.into())
 }
 
-pub fn is_game_running() -> bool {
-    let Ok(snapshot) = (unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPALL, 0) }) else {
-        return false;
-    };
+fn find_game_process_id() -> Option<u32> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPALL, 0).ok()? };
     let mut entry = PROCESSENTRY32::default();
     entry.dwSize = std::mem::size_of::<PROCESSENTRY32>() as u32;
     let mut res = unsafe { Process32First(snapshot, &mut entry) };
 
+    let mut process_id = None;
     while res.is_ok() {
         let process_name = unsafe { CStr::from_ptr(entry.szExeFile.as_ptr()) };
         if process_name == c"umamusume.exe" {
-            return true;
+            process_id = Some(entry.th32ProcessID);
+            break;
         }
 
         res = unsafe { Process32Next(snapshot, &mut entry) };
     }
 
-    false
+    unsafe { _ = CloseHandle(snapshot) };
+    process_id
+}
+
+pub fn is_game_running() -> bool {
+    find_game_process_id().is_some()
+}
+
+pub fn get_game_process_path() -> Option<PathBuf> {
+    let process_id = find_game_process_id()?;
+    let process =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()? };
+
+    let mut buffer = [0u16; MAX_PATH as usize];
+    let mut length = buffer.len() as u32;
+    let res = unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut length,
+        )
+    };
+    unsafe { _ = CloseHandle(process) };
+    res.ok()?;
+
+    Some(PathBuf::from(OsString::from_wide(&buffer[0..length as usize])))
+}
+
+pub fn get_game_process_machine() -> Option<u16> {
+    let process_id = find_game_process_id()?;
+    let process =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()? };
+
+    let machine = get_process_machine(process);
+    unsafe { _ = CloseHandle(process) };
+    machine
+}
+
+fn get_process_machine(process: HANDLE) -> Option<u16> {
+    // Reports x64 processes emulated on ARM64 correctly, but needs Windows 11
+    let mut machine_info = PROCESS_MACHINE_INFORMATION::default();
+    let res = unsafe {
+        GetProcessInformation(
+            process,
+            ProcessMachineTypeInfo,
+            &mut machine_info as *mut _ as *mut c_void,
+            std::mem::size_of::<PROCESS_MACHINE_INFORMATION>() as u32,
+        )
+    };
+    if res.is_ok() {
+        return Some(machine_info.ProcessMachine.0);
+    }
+
+    // IsWow64Process2 only reports WOW64 processes, emulated x64 shows up as the native arch
+    let mut process_machine = IMAGE_FILE_MACHINE::default();
+    let mut native_machine = IMAGE_FILE_MACHINE::default();
+    unsafe { IsWow64Process2(process, &mut process_machine, Some(&mut native_machine)).ok()? };
+
+    if process_machine == IMAGE_FILE_MACHINE_UNKNOWN {
+        Some(native_machine.0)
+    } else {
+        Some(process_machine.0)
+    }
 }
 
 pub fn get_system_directory() -> PathBuf {
@@ -113,3 +214,205 @@ pub fn get_system_directory() -> PathBuf {
     let length = unsafe { GetSystemDirectoryW(Some(&mut buffer)) };
     PathBuf::from(OsString::from_wide(&buffer[0..length as usize]))
 }
+
+pub fn get_exe_dir() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.to_owned())
+}
+
+pub fn get_app_data_dir() -> Option<PathBuf> {
+    let path_wstr =
+        unsafe { SHGetKnownFolderPath(&FOLDERID_RoamingAppData, KF_FLAG_DEFAULT, None).ok()? };
+    let path_str = unsafe { path_wstr.to_string().ok()? };
+    Some(path_str.into())
+}
+
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    semver::Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+pub fn to_extended_path(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+
+    let mut extended_path = match prefix.kind() {
+        Prefix::Disk(_) => {
+            let mut path_str = OsString::from(r"\\?\");
+            path_str.push(prefix.as_os_str());
+            PathBuf::from(path_str)
+        }
+        // \\server\share becomes \\?\UNC\server\share
+        Prefix::UNC(server, share) => {
+            let mut path_str = OsString::from(r"\\?\UNC\");
+            path_str.push(server);
+            path_str.push(r"\");
+            path_str.push(share);
+            PathBuf::from(path_str)
+        }
+        // Already extended or a device path
+        _ => return path.to_owned(),
+    };
+
+    // Extended paths are passed to the filesystem as-is, so they have to be normalized beforehand
+    for component in components {
+        match component {
+            Component::RootDir => extended_path.push(r"\"),
+            Component::CurDir | Component::Prefix(_) => {}
+            Component::ParentDir => {
+                extended_path.pop();
+            }
+            Component::Normal(name) => extended_path.push(name),
+        }
+    }
+    extended_path
+}
+
+// fs::canonicalize returns extended paths, turn them back into regular ones where possible
+pub fn strip_extended_prefix(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+
+    let mut stripped = match prefix.kind() {
+        Prefix::VerbatimDisk(letter) => PathBuf::from(format!("{}:", letter as char)),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut path_str = OsString::from(r"\\");
+            path_str.push(server);
+            path_str.push(r"\");
+            path_str.push(share);
+            PathBuf::from(path_str)
+        }
+        _ => return path.to_owned(),
+    };
+    stripped.extend(components);
+    stripped
+}
+
+// Returns (available, total) bytes for the current user
+pub fn get_disk_space<P: AsRef<Path>>(path: P) -> Option<(u64, u64)> {
+    let mut available = 0u64;
+    let mut total = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            &HSTRING::from(with_trailing_separator(path.as_ref()).as_os_str()),
+            Some(&mut available),
+            Some(&mut total),
+            None,
+        )
+        .ok()?
+    };
+    Some((available, total))
+}
+
+// GetDiskFreeSpaceExW fails on UNC roots without a trailing backslash, other directories don't mind it
+fn with_trailing_separator(path: &Path) -> OsString {
+    let mut path_str = path.as_os_str().to_owned();
+    if !path_str.to_string_lossy().ends_with(['\\', '/']) {
+        path_str.push(r"\");
+    }
+    path_str
+}
+
+const DRIVE_REMOVABLE: u32 = 2;
+
+pub fn is_removable_drive<P: AsRef<Path>>(path: P) -> bool {
+    let mut volume_path = [0u16; MAX_PATH as usize];
+    let res = unsafe {
+        GetVolumePathNameW(&HSTRING::from(path.as_ref().as_os_str()), &mut volume_path)
+    };
+    if res.is_err() {
+        return false;
+    }
+    unsafe { GetDriveTypeW(PCWSTR(volume_path.as_ptr())) == DRIVE_REMOVABLE }
+}
+
+// The installer is a GUI app, so it has no console to print to unless it borrows the parent's
+pub fn attach_parent_console() {
+    unsafe { _ = AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+pub fn is_running_under_wine() -> bool {
+    let Ok(ntdll) = (unsafe { GetModuleHandleW(w!("ntdll.dll")) }) else {
+        return false;
+    };
+    unsafe { GetProcAddress(ntdll, s!("wine_get_version")) }.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn extended_path_from_disk_path() {
+        assert_eq!(
+            to_extended_path(Path::new(r"C:\game\umamusume.exe")),
+            PathBuf::from(r"\\?\C:\game\umamusume.exe")
+        );
+    }
+
+    #[test]
+    fn extended_path_is_normalized() {
+        assert_eq!(
+            to_extended_path(Path::new(r"C:\game\.\hachimi\..\umamusume.exe")),
+            PathBuf::from(r"\\?\C:\game\umamusume.exe")
+        );
+    }
+
+    #[test]
+    fn extended_path_keeps_verbatim_path() {
+        let path = Path::new(r"\\?\C:\game\umamusume.exe");
+        assert_eq!(to_extended_path(path), path);
+    }
+
+    #[test]
+    fn extended_path_from_long_path() {
+        let dir_name = "a".repeat(300);
+        let path = Path::new(r"C:\game").join(&dir_name).join("UnityPlayer.dll");
+        assert!(path.as_os_str().len() > MAX_PATH as usize);
+
+        assert_eq!(
+            to_extended_path(&path),
+            PathBuf::from(format!(r"\\?\C:\game\{}\UnityPlayer.dll", dir_name))
+        );
+    }
+
+    #[test]
+    fn extended_path_from_unc_path() {
+        assert_eq!(
+            to_extended_path(Path::new(r"\\server\share\dir")),
+            PathBuf::from(r"\\?\UNC\server\share\dir")
+        );
+    }
+
+    #[test]
+    fn disk_space_path_has_trailing_separator() {
+        assert_eq!(with_trailing_separator(Path::new(r"\\server\share")), r"\\server\share\");
+        assert_eq!(with_trailing_separator(Path::new(r"\\?\UNC\server\share\dir")), r"\\?\UNC\server\share\dir\");
+        assert_eq!(with_trailing_separator(Path::new(r"C:\game\")), r"C:\game\");
+    }
+
+    #[test]
+    fn disk_space_of_unc_root() {
+        // Goes through the system drive's admin share, which might be disabled
+        let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_owned());
+        let share = PathBuf::from(format!(r"\\localhost\{}$", drive.trim_end_matches(':')));
+        if !share.is_dir() {
+            return;
+        }
+
+        assert!(get_disk_space(&share).is_some());
+        assert!(get_disk_space(to_extended_path(&share)).is_some());
+    }
+
+    #[test]
+    fn strip_extended_prefix_round_trip() {
+        for path in [r"C:\game\umamusume.exe", r"\\server\share\dir\umamusume.exe"] {
+            let path = Path::new(path);
+            assert_eq!(strip_extended_prefix(&to_extended_path(path)), path);
+        }
+    }
+}
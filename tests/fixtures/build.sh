@@ -0,0 +1,11 @@
+#!/bin/sh
+# Rebuilds the fixture DLLs from their .rc files. They only contain a version resource.
+set -e
+cd "$(dirname "$0")"
+for rc in *.rc; do
+    name="${rc%.rc}"
+    llvm-rc /no-preprocess /FO "$name.res" "$rc"
+    llvm-cvtres /MACHINE:X64 /OUT:"$name.obj" "$name.res"
+    rust-lld -flavor link /DLL /NOENTRY /MACHINE:X64 /NOIMPLIB /OUT:"$name.dll" "$name.obj"
+    rm "$name.res" "$name.obj"
+done